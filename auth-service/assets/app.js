@@ -5,6 +5,27 @@ const signupSection = document.getElementById("signup-section");
 const signupLink = document.getElementById("signup-link");
 const twoFALoginLink = document.getElementById("2fa-login-link");
 const signupLoginLink = document.getElementById("signup-login-link");
+const forgotSection = document.getElementById("forgot-section");
+const resetSection = document.getElementById("reset-section");
+const forgotLink = document.getElementById("forgot-link");
+const forgotLoginLink = document.getElementById("forgot-login-link");
+const consentSection = document.getElementById("consent-section");
+
+function showSection(section) {
+    for (const each of [loginSection, twoFASection, signupSection, forgotSection, resetSection, consentSection]) {
+        each.style.display = each === section ? "block" : "none";
+    }
+}
+
+forgotLink.addEventListener("click", (e) => {
+    e.preventDefault();
+    showSection(forgotSection);
+});
+
+forgotLoginLink.addEventListener("click", (e) => {
+    e.preventDefault();
+    showSection(loginSection);
+});
 
 signupLink.addEventListener("click", (e) => {
     e.preventDefault();
@@ -66,7 +87,7 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            loggedIn();
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -112,7 +133,14 @@ signupButton.addEventListener("click", (e) => {
             response.json().then(data => {
                 let error_msg = data.error;
                 if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
-                    signupErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    let feedback = "";
+                    if (data.warning) {
+                        feedback += `<br><span>${data.warning}</span>`;
+                    }
+                    if (Array.isArray(data.suggestions) && data.suggestions.length > 0) {
+                        feedback += `<ul class="mb-0 text-start">${data.suggestions.map(s => `<li>${s}</li>`).join("")}</ul>`;
+                    }
+                    signupErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>${feedback}`;
                     signupErrAlter.style.display = "block";
                 } else {
                     signupErrAlter.style.display = "none";
@@ -145,10 +173,7 @@ TwoFAButton.addEventListener("click", (e) => {
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
-            loginSection.style.display = "block";
-            twoFASection.style.display = "none";
-            signupSection.style.display = "none";
+            loggedIn();
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -161,4 +186,185 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+
+const forgotForm = document.getElementById("forgot-form");
+const forgotButton = document.getElementById("forgot-form-submit");
+const forgotErrAlter = document.getElementById("forgot-err-alert");
+
+forgotButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const email = forgotForm.email.value;
+
+    fetch('/forgot-password', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ email }),
+    }).then(response => {
+        response.json().then(data => {
+            if (response.ok) {
+                forgotForm.email.value = "";
+                forgotErrAlter.style.display = "none";
+                alert(data.message);
+                showSection(loginSection);
+            } else if (data.error) {
+                forgotErrAlter.innerHTML = `<span><strong>Error: </strong>${data.error}</span>`;
+                forgotErrAlter.style.display = "block";
+            } else {
+                forgotErrAlter.style.display = "none";
+            }
+        });
+    });
+});
+
+const resetForm = document.getElementById("reset-form");
+const resetButton = document.getElementById("reset-form-submit");
+const resetErrAlter = document.getElementById("reset-err-alert");
+
+// the link from the reset email lands here
+const resetToken = new URLSearchParams(window.location.search).get("reset_token");
+if (resetToken) {
+    resetForm.token.value = resetToken;
+    showSection(resetSection);
+}
+
+resetButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const token = resetForm.token.value;
+    const newPassword = resetForm.password.value;
+
+    fetch('/reset-password', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token, newPassword }),
+    }).then(response => {
+        response.json().then(data => {
+            if (response.ok) {
+                resetForm.token.value = "";
+                resetForm.password.value = "";
+                resetErrAlter.style.display = "none";
+                window.history.replaceState(null, "", "/");
+                alert("Your password has been reset, please log in.");
+                showSection(loginSection);
+            } else if (data.error) {
+                let feedback = "";
+                if (data.warning) {
+                    feedback += `<br><span>${data.warning}</span>`;
+                }
+                if (Array.isArray(data.suggestions) && data.suggestions.length > 0) {
+                    feedback += `<ul class="mb-0 text-start">${data.suggestions.map(s => `<li>${s}</li>`).join("")}</ul>`;
+                }
+                resetErrAlter.innerHTML = `<span><strong>Error: </strong>${data.error}</span>${feedback}`;
+                resetErrAlter.style.display = "block";
+            } else {
+                resetErrAlter.style.display = "none";
+            }
+        });
+    });
+});
+
+// `/authorize` sends the browser here with the request of an OAuth client, it is answered once logged in
+const oauthParams = new URLSearchParams(window.location.search);
+const oauthRequest = oauthParams.get("oauth_request");
+// `/device/verify` does the same for a device waiting for its user
+const deviceUserCode = oauthParams.get("device_user_code");
+const consentErrAlter = document.getElementById("consent-err-alert");
+// the name shown comes from the server, a crafted link can't make a client pass for another
+if (oauthRequest) {
+    const clientId = new URLSearchParams(oauthRequest).get("client_id") || "";
+    fetch(`/clients/${encodeURIComponent(clientId)}`).then(response => {
+        if (response.ok) {
+            response.json().then(data => {
+                document.getElementById("consent-client").textContent = data.name;
+            });
+        }
+    });
+}
+if (deviceUserCode) {
+    document.getElementById("consent-user-code").textContent = deviceUserCode;
+    document.getElementById("consent-device").style.display = "block";
+    fetch(`/device/request?user_code=${encodeURIComponent(deviceUserCode)}`).then(response => {
+        response.json().then(data => {
+            if (response.ok) {
+                document.getElementById("consent-client").textContent = data.name;
+            } else {
+                consentErrAlter.innerHTML = `<span><strong>Error: </strong>${data.error_description || data.error}</span>`;
+                consentErrAlter.style.display = "block";
+            }
+        });
+    });
+}
+
+function loggedIn() {
+    if (oauthRequest || deviceUserCode) {
+        showSection(consentSection);
+        return;
+    }
+    alert("You have successfully logged in.");
+    showSection(loginSection);
+}
+
+function answerDevice(approved) {
+    fetch('/device/verify', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ userCode: deviceUserCode, approved }),
+    }).then(response => {
+        if (response.ok) {
+            consentErrAlter.style.display = "none";
+            window.history.replaceState(null, "", "/");
+            alert(approved ? "Your device is signed in, you can go back to it." : "Your device was denied access.");
+            showSection(loginSection);
+        } else {
+            response.json().then(data => {
+                consentErrAlter.innerHTML = `<span><strong>Error: </strong>${data.error_description || data.error}</span>`;
+                consentErrAlter.style.display = "block";
+            });
+        }
+    });
+}
+
+function answerConsent(approved) {
+    if (deviceUserCode) {
+        answerDevice(approved);
+        return;
+    }
+    const request = Object.fromEntries(new URLSearchParams(oauthRequest));
+
+    fetch('/authorize', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ ...request, approved }),
+    }).then(response => {
+        response.json().then(data => {
+            if (response.ok) {
+                window.location.assign(data.redirectTo);
+            } else if (data.error_description || data.error) {
+                consentErrAlter.innerHTML = `<span><strong>Error: </strong>${data.error_description || data.error}</span>`;
+                consentErrAlter.style.display = "block";
+            } else {
+                consentErrAlter.style.display = "none";
+            }
+        });
+    });
+}
+
+document.getElementById("consent-allow").addEventListener("click", (e) => {
+    e.preventDefault();
+    answerConsent(true);
+});
+
+document.getElementById("consent-deny").addEventListener("click", (e) => {
+    e.preventDefault();
+    answerConsent(false);
+});
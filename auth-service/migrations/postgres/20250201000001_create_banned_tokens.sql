@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS banned_tokens (
+    token TEXT NOT NULL PRIMARY KEY,
+    banned_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- tokens of `subject` issued before `issued_before` (seconds since epoch) are revoked
+CREATE TABLE IF NOT EXISTS revoked_sessions (
+    subject TEXT NOT NULL PRIMARY KEY,
+    issued_before BIGINT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- seconds since epoch, rows past it can be deleted
+ALTER TABLE banned_tokens ADD COLUMN IF NOT EXISTS expires_at BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- tokens are banned by their `jti` claim now, full JWTs banned before can't match anymore
+DELETE FROM banned_tokens;
+ALTER TABLE banned_tokens RENAME COLUMN token TO jti;
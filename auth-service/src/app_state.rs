@@ -1,22 +1,69 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::services::{hashmap_user_store::HashmapUserStore, HashsetBannedTokenStore};
+use crate::{
+    domain::{
+        AuthorizationCodeStore, BannedTokenStore, ClientStore, DeviceCodeStore, EmailClient,
+        PasswordPolicy, PasswordResetTokenStore, RefreshTokenStore, TwoFACodeStore, UserStore,
+    },
+    utils::keyring::Keyring,
+};
 
-pub type UserStoreType = Arc<RwLock<HashmapUserStore>>;
-pub type BannedTokensType = Arc<RwLock<HashsetBannedTokenStore>>;
+pub type UserStoreType = Arc<RwLock<dyn UserStore>>;
+pub type BannedTokensType = Arc<RwLock<dyn BannedTokenStore>>;
+pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore>>;
+pub type PasswordResetTokenStoreType = Arc<RwLock<dyn PasswordResetTokenStore>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore>>;
+pub type ClientStoreType = Arc<RwLock<dyn ClientStore>>;
+pub type AuthorizationCodeStoreType = Arc<RwLock<dyn AuthorizationCodeStore>>;
+pub type DeviceCodeStoreType = Arc<RwLock<dyn DeviceCodeStore>>;
+pub type EmailClientType = Arc<dyn EmailClient>;
+pub type PasswordPolicyType = Arc<PasswordPolicy>;
+// Swapped as a whole when the keys are reloaded
+pub type KeyringType = Arc<RwLock<Keyring>>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_tokens: BannedTokensType,
+    pub two_fa_code_store: TwoFACodeStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub client_store: ClientStoreType,
+    pub authorization_code_store: AuthorizationCodeStoreType,
+    pub device_code_store: DeviceCodeStoreType,
+    pub email_client: EmailClientType,
+    pub password_policy: PasswordPolicyType,
+    pub keyring: KeyringType,
 }
 
 impl AppState {
-    pub fn new(user_store: UserStoreType, banned_tokens: BannedTokensType) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_store: UserStoreType,
+        banned_tokens: BannedTokensType,
+        two_fa_code_store: TwoFACodeStoreType,
+        password_reset_token_store: PasswordResetTokenStoreType,
+        refresh_token_store: RefreshTokenStoreType,
+        client_store: ClientStoreType,
+        authorization_code_store: AuthorizationCodeStoreType,
+        device_code_store: DeviceCodeStoreType,
+        email_client: EmailClientType,
+        password_policy: PasswordPolicyType,
+        keyring: KeyringType,
+    ) -> Self {
         Self {
             user_store,
             banned_tokens,
+            two_fa_code_store,
+            password_reset_token_store,
+            refresh_token_store,
+            client_store,
+            authorization_code_store,
+            device_code_store,
+            email_client,
+            password_policy,
+            keyring,
         }
     }
 }
@@ -1,10 +1,21 @@
-use super::{CreateUserError, User};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use super::{
+    CodeChallenge, CreateUserError, Email, HashedPassword, OAuthClient, PasswordHashError,
+    TwoFAError, User,
+};
 
 #[async_trait::async_trait]
 pub trait UserStore: Send + Sync {
     async fn add_user(&mut self, _user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, _email: &str) -> Result<User, UserStoreError>;
     async fn validate_user(&self, _email: &str, _password: &str) -> Result<(), UserStoreError>;
+    async fn update_password(
+        &mut self,
+        _email: &str,
+        _password: HashedPassword,
+    ) -> Result<(), UserStoreError>;
 }
 
 #[derive(thiserror::Error, Debug, PartialEq)]
@@ -19,6 +30,10 @@ pub enum UserStoreError {
     UnexpectedError(#[from] CreateUserError),
     #[error("invalid user")]
     UnableToCreateUser,
+    #[error("Unable to check password")]
+    PasswordHash(PasswordHashError),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
 }
 
 #[derive(thiserror::Error, Debug, PartialEq)]
@@ -31,10 +46,527 @@ pub enum BannedTokenError {
     InvalidInput,
     #[error("Mutex lock poisoned")]
     Poisoned,
+    #[error("Database error: {0}")]
+    DatabaseError(String),
 }
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore: Send + Sync {
-    async fn add(&mut self, _data: String) -> Result<(), BannedTokenError>;
-    async fn check(&self, _data: String) -> Result<(), BannedTokenError>;
+    // Tokens are banned by their `jti` claim.
+    // `expires_at` is the token's `exp` claim, a banned token needn't be kept past it
+    async fn add(&mut self, jti: String, expires_at: usize) -> Result<(), BannedTokenError>;
+    async fn check(&self, jti: String) -> Result<(), BannedTokenError>;
+    // Revoke every token of `subject` issued before `issued_before` (seconds since epoch)
+    async fn revoke_issued_before(
+        &mut self,
+        subject: String,
+        issued_before: usize,
+    ) -> Result<(), BannedTokenError>;
+    async fn check_issued(&self, subject: &str, issued_at: usize) -> Result<(), BannedTokenError>;
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum TwoFACodeStoreError {
+    #[error("Login attempt not found")]
+    LoginAttemptIdNotFound,
+    #[error("Login attempt expired")]
+    Expired,
+    #[error("Incorrect 2FA code")]
+    IncorrectCode,
+    #[error("Too many failed attempts")]
+    TooManyAttempts,
+    #[error("Something went wrong")]
+    UnexpectedError,
+}
+
+#[async_trait::async_trait]
+pub trait TwoFACodeStore: Send + Sync {
+    async fn add_code(
+        &mut self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    /// Checks a pending login attempt and consumes it on success.
+    /// Every wrong guess is counted, the attempt is dropped once it is used up or expired.
+    async fn verify_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError>;
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum PasswordResetTokenStoreError {
+    #[error("Invalid reset token")]
+    InvalidToken,
+    #[error("Reset token not found")]
+    TokenNotFound,
+    #[error("Reset token expired")]
+    Expired,
+    #[error("Something went wrong")]
+    UnexpectedError,
+}
+
+// Implementations only keep `PasswordResetToken::hash`, never the token itself
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore: Send + Sync {
+    /// Stores a new token for `email`, replacing any older one.
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: &PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+    async fn get_email(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError>;
+    /// Removes the token and returns its owner, a token can be consumed only once.
+    async fn consume_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError>;
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum RefreshTokenStoreError {
+    #[error("Invalid refresh token")]
+    InvalidToken,
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token expired")]
+    Expired,
+    #[error("Refresh token was already used")]
+    Reused,
+    #[error("Refresh token was issued to another client")]
+    ClientMismatch,
+    #[error("Something went wrong")]
+    UnexpectedError,
+}
+
+// How and when the user of a session logged in, reported as OIDC's `auth_time` and `amr`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Authentication {
+    pub auth_time: usize,
+    // RFC 8176 method references, e.g. `pwd` or `otp`
+    pub amr: Vec<String>,
+}
+
+impl Authentication {
+    // A login that just succeeded, with or without an emailed 2FA code
+    pub fn now(two_factor: bool) -> Self {
+        let amr = if two_factor {
+            vec!["pwd", "otp", "mfa"]
+        } else {
+            vec!["pwd"]
+        };
+
+        Self {
+            auth_time: usize::try_from(chrono::Utc::now().timestamp()).unwrap_or_default(),
+            amr: amr.into_iter().map(str::to_owned).collect(),
+        }
+    }
+}
+
+// OAuth client a token family was issued to at `/token`, with the scope the user granted it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshTokenClient {
+    pub client_id: String,
+    pub scope: Option<String>,
+}
+
+// A login starts a token family, each refresh replaces the family's current token by a new one.
+// Implementations only keep `RefreshToken::hash`, never the token itself
+#[async_trait::async_trait]
+pub trait RefreshTokenStore: Send + Sync {
+    /// Starts a new family for `email` with `token` as its current token,
+    /// issued to an OAuth client or to a login session when `client` is `None`.
+    async fn add_token(
+        &mut self,
+        email: Email,
+        authentication: Authentication,
+        client: Option<RefreshTokenClient>,
+        token: &RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError>;
+    /// Replaces `token` by `new_token` in its family and returns the owner and how they logged in.
+    /// A token that was already rotated is being replayed, its whole family gets revoked.
+    /// A family of another client than `client_id` (`None` for a login session) fails
+    /// with `ClientMismatch` and is left as it was.
+    async fn rotate_token(
+        &mut self,
+        token: &RefreshToken,
+        client_id: Option<&str>,
+        new_token: &RefreshToken,
+    ) -> Result<(Email, Authentication), RefreshTokenStoreError>;
+    /// Returns the OAuth client the family of `token` was issued to, `None` for a login session.
+    async fn get_client(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<Option<RefreshTokenClient>, RefreshTokenStoreError>;
+    /// Revokes the family `token` belongs to, e.g. on logout.
+    async fn revoke_family(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError>;
+    /// Revokes every family of `email`.
+    async fn revoke_all(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ClientStoreError {
+    #[error("Client already exists")]
+    ClientAlreadyExists,
+    #[error("Client not found")]
+    ClientNotFound,
+    #[error("Something went wrong")]
+    UnexpectedError,
+}
+
+// Applications registered to use the OAuth endpoints
+#[async_trait::async_trait]
+pub trait ClientStore: Send + Sync {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), ClientStoreError>;
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, ClientStoreError>;
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum AuthorizationCodeStoreError {
+    #[error("Invalid authorization code")]
+    InvalidCode,
+    #[error("Authorization code not found")]
+    CodeNotFound,
+    #[error("Authorization code expired")]
+    Expired,
+    #[error("Something went wrong")]
+    UnexpectedError,
+}
+
+// What the user agreed to on the consent page, redeemed at `/token`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizationGrant {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub email: Email,
+    pub code_challenge: CodeChallenge,
+    pub scope: Option<String>,
+    // OIDC: echoed in the ID token, along with how the user logged in
+    pub nonce: Option<String>,
+    pub authentication: Authentication,
+}
+
+impl AuthorizationGrant {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope
+            .as_deref()
+            .is_some_and(|scopes| scopes.split_whitespace().any(|each| each == scope))
+    }
+}
+
+// Implementations only keep `AuthorizationCode::hash`, never the code itself
+#[async_trait::async_trait]
+pub trait AuthorizationCodeStore: Send + Sync {
+    async fn add_code(
+        &mut self,
+        code: &AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError>;
+    /// Removes the code and returns its grant, a code can be redeemed only once.
+    async fn take_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError>;
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum DeviceCodeStoreError {
+    #[error("Invalid device code")]
+    InvalidCode,
+    #[error("Invalid user code")]
+    InvalidUserCode,
+    #[error("Device code not found")]
+    CodeNotFound,
+    #[error("User code already in use")]
+    UserCodeInUse,
+    #[error("Device code expired")]
+    Expired,
+    #[error("The user hasn't answered yet")]
+    AuthorizationPending,
+    #[error("The device polls too often")]
+    SlowDown,
+    #[error("The user denied the request")]
+    AccessDenied,
+    #[error("Device code was issued to another client")]
+    ClientMismatch,
+    #[error("Something went wrong")]
+    UnexpectedError,
+}
+
+// What a device asked for at `/device_authorization`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceRequest {
+    pub client_id: String,
+    pub scope: Option<String>,
+}
+
+// A device request the user approved on the verification page, redeemed at `/token`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceGrant {
+    pub request: DeviceRequest,
+    pub email: Email,
+    pub authentication: Authentication,
+}
+
+// The device polls with its `DeviceCode` while the user answers with the `UserCode` it displays.
+// Implementations only keep `DeviceCode::hash`, never the code itself
+#[async_trait::async_trait]
+pub trait DeviceCodeStore: Send + Sync {
+    async fn add_request(
+        &mut self,
+        device_code: &DeviceCode,
+        user_code: &UserCode,
+        request: DeviceRequest,
+    ) -> Result<(), DeviceCodeStoreError>;
+    /// The request still waiting for an answer that `user_code` belongs to.
+    async fn get_request(
+        &self,
+        user_code: &UserCode,
+    ) -> Result<DeviceRequest, DeviceCodeStoreError>;
+    async fn approve(
+        &mut self,
+        user_code: &UserCode,
+        email: Email,
+        authentication: Authentication,
+    ) -> Result<(), DeviceCodeStoreError>;
+    async fn deny(&mut self, user_code: &UserCode) -> Result<(), DeviceCodeStoreError>;
+    /// Returns the grant once the user approved, a grant can be redeemed only once.
+    /// Until then fails with `AuthorizationPending`, or `SlowDown` when polled too often.
+    /// A client other than `client_id` gets `ClientMismatch` and leaves the request as it was.
+    async fn poll(
+        &mut self,
+        device_code: &DeviceCode,
+        client_id: &str,
+    ) -> Result<DeviceGrant, DeviceCodeStoreError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginAttemptId(String);
+
+impl LoginAttemptId {
+    pub fn parse(id: &str) -> Result<Self, TwoFAError> {
+        let id = uuid::Uuid::parse_str(id).map_err(|_| TwoFAError::InvalidLoginAttemptId)?;
+        Ok(Self(id.to_string()))
+    }
+}
+
+impl Default for LoginAttemptId {
+    fn default() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for LoginAttemptId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TwoFACode(String);
+
+impl TwoFACode {
+    pub fn parse(code: &str) -> Result<Self, TwoFAError> {
+        let is_six_digits = code.len() == 6 && code.chars().all(|c| c.is_ascii_digit());
+
+        if !is_six_digits {
+            return Err(TwoFAError::InvalidTwoFACode);
+        }
+        Ok(Self(code.to_string()))
+    }
+}
+
+impl Default for TwoFACode {
+    fn default() -> Self {
+        let code: u32 = rand::thread_rng().gen_range(0..1_000_000);
+        Self(format!("{code:06}"))
+    }
+}
+
+impl AsRef<str> for TwoFACode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// 256 random bits, hex encoded so they can travel in a link, a cookie or a form.
+// SHA-256 is enough for `hash`, the tokens are random and not passwords.
+macro_rules! opaque_token {
+    ($name:ident, $error:ident::$invalid:ident) => {
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn parse(token: &str) -> Result<Self, $error> {
+                let is_valid = token.len() == 64 && token.chars().all(|c| c.is_ascii_hexdigit());
+
+                if !is_valid {
+                    return Err($error::$invalid);
+                }
+                Ok(Self(token.to_ascii_lowercase()))
+            }
+
+            pub fn hash(&self) -> String {
+                to_hex(&Sha256::digest(self.0.as_bytes()))
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                let bytes: [u8; 32] = rand::thread_rng().gen();
+                Self(to_hex(&bytes))
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+    };
+}
+
+// Sent by email in the password reset link
+opaque_token!(
+    PasswordResetToken,
+    PasswordResetTokenStoreError::InvalidToken
+);
+// Rotated on every use
+opaque_token!(RefreshToken, RefreshTokenStoreError::InvalidToken);
+// Short lived and single use
+opaque_token!(AuthorizationCode, AuthorizationCodeStoreError::InvalidCode);
+// Only ever seen by the device
+opaque_token!(DeviceCode, DeviceCodeStoreError::InvalidCode);
+
+// RFC 8628 section 6.1: consonants only, so no words and no 0/O or 1/I mix-ups
+const USER_CODE_CHARSET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+// Typed in by the user on the verification page, shown as `XXXX-XXXX`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserCode(String);
+
+impl UserCode {
+    // Lenient on what users type: case, dashes and spaces don't matter
+    pub fn parse(code: &str) -> Result<Self, DeviceCodeStoreError> {
+        let chars: Vec<u8> = code
+            .bytes()
+            .filter(|b| *b != b'-' && !b.is_ascii_whitespace())
+            .map(|b| b.to_ascii_uppercase())
+            .collect();
+
+        if chars.len() != 8 || !chars.iter().all(|b| USER_CODE_CHARSET.contains(b)) {
+            return Err(DeviceCodeStoreError::InvalidUserCode);
+        }
+        let chars = String::from_utf8(chars).map_err(|_| DeviceCodeStoreError::InvalidUserCode)?;
+        Ok(Self(format!("{}-{}", &chars[..4], &chars[4..])))
+    }
+}
+
+impl Default for UserCode {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        let chars: String = (0..8)
+            .map(|_| char::from(USER_CODE_CHARSET[rng.gen_range(0..USER_CODE_CHARSET.len())]))
+            .collect();
+        Self(format!("{}-{}", &chars[..4], &chars[4..]))
+    }
+}
+
+impl AsRef<str> for UserCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_attempt_id_shall_accept_uuid() {
+        let id = LoginAttemptId::default();
+        assert_eq!(LoginAttemptId::parse(id.as_ref()), Ok(id));
+    }
+
+    #[test]
+    fn login_attempt_id_shall_reject_garbage() {
+        assert_eq!(
+            LoginAttemptId::parse("not-a-uuid"),
+            Err(TwoFAError::InvalidLoginAttemptId)
+        );
+    }
+
+    #[test]
+    fn two_fa_code_shall_be_six_digits() {
+        let code = TwoFACode::default();
+        assert_eq!(code.as_ref().len(), 6);
+        assert_eq!(TwoFACode::parse(code.as_ref()), Ok(code));
+    }
+
+    // every opaque token type comes from `opaque_token!`, one of them is enough to test
+    #[test]
+    fn opaque_token_shall_be_random_hex() {
+        let token = PasswordResetToken::default();
+        assert_eq!(token.as_ref().len(), 64);
+        assert_ne!(token, PasswordResetToken::default());
+        assert_eq!(PasswordResetToken::parse(token.as_ref()), Ok(token));
+    }
+
+    #[test]
+    fn opaque_token_shall_reject_invalid_input() {
+        for each in ["", "abc", &"g".repeat(64), &"a".repeat(65)] {
+            assert_eq!(
+                PasswordResetToken::parse(each),
+                Err(PasswordResetTokenStoreError::InvalidToken)
+            );
+        }
+    }
+
+    #[test]
+    fn opaque_token_hash_shall_not_be_the_token() {
+        let token = PasswordResetToken::default();
+        assert_eq!(token.hash().len(), 64);
+        assert_ne!(token.hash(), token.as_ref());
+        assert_eq!(token.hash(), token.clone().hash());
+    }
+
+    #[test]
+    fn user_code_shall_accept_what_users_type() {
+        let code = UserCode::default();
+        assert_eq!(code.as_ref().len(), 9);
+        assert_eq!(UserCode::parse(code.as_ref()), Ok(code));
+
+        let expected = UserCode::parse("BCDF-GHJK").unwrap();
+        for each in ["bcdfghjk", "bcdf ghjk", " BCDF-ghjk "] {
+            assert_eq!(UserCode::parse(each), Ok(expected.clone()));
+        }
+        for each in ["BCDF-GHJ", "BCDF-GHJKL", "ABCD-EFGH", "BCDF-GHJ1"] {
+            assert_eq!(
+                UserCode::parse(each),
+                Err(DeviceCodeStoreError::InvalidUserCode)
+            );
+        }
+    }
+
+    #[test]
+    fn two_fa_code_shall_reject_invalid_input() {
+        for each in ["12345", "1234567", "12a456", ""] {
+            assert_eq!(TwoFACode::parse(each), Err(TwoFAError::InvalidTwoFACode));
+        }
+    }
 }
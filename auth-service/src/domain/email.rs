@@ -21,6 +21,17 @@ impl Email {
         }
     }
 
+    // The address and its pieces, a password built from them is easy to guess
+    pub fn password_context(&self) -> Vec<&str> {
+        let mut words = vec![self.0.as_str()];
+        words.extend(
+            self.0
+                .split(['@', '.', '_', '-', '+'])
+                .filter(|word| !word.is_empty()),
+        );
+        words
+    }
+
     // pub fn as_str(&self) -> &str {
     //     &self.0
     // }
@@ -49,6 +60,23 @@ mod tests {
     //     assert_eq!(mail, Err(CreateUserError::InvalidEmail));
     // }
 
+    #[test]
+    fn password_context_splits_address() {
+        let email = Email::parse("zaphod.beeblebrox@heart-of-gold.com").unwrap();
+        assert_eq!(
+            email.password_context(),
+            vec![
+                "zaphod.beeblebrox@heart-of-gold.com",
+                "zaphod",
+                "beeblebrox",
+                "heart",
+                "of",
+                "gold",
+                "com"
+            ]
+        );
+    }
+
     #[test]
     fn happy_case() {
         let email = Email::parse("testing@gmail.com");
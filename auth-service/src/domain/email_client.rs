@@ -0,0 +1,28 @@
+use super::Email;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum EmailClientError {
+    #[error("Invalid email message: {0}")]
+    InvalidMessage(String),
+    #[error("Unable to deliver email: {0}")]
+    DeliveryFailed(String),
+    #[error("Invalid email client configuration: {0}")]
+    InvalidConfig(String),
+}
+
+// A rendered message, every email is sent with both an HTML and a plain text part
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+#[async_trait::async_trait]
+pub trait EmailClient: Send + Sync {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<(), EmailClientError>;
+}
@@ -1,3 +1,5 @@
+use super::PasswordFeedback;
+
 #[derive(Debug, thiserror::Error)]
 pub enum AuthAPIError {
     #[error("user already exists")]
@@ -18,6 +20,10 @@ pub enum AuthAPIError {
     InvalidToken,
     #[error("malformed token")]
     MalformedToken,
+    #[error("weak password")]
+    WeakPassword(PasswordFeedback),
+    #[error("breached password")]
+    BreachedPassword,
 }
 
 #[derive(thiserror::Error, Debug, PartialEq)]
@@ -26,4 +32,67 @@ pub enum CreateUserError {
     InvalidPassword,
     #[error("Invalid email")]
     InvalidEmail,
+    #[error("Password is too weak")]
+    WeakPassword(PasswordFeedback),
+    #[error("Password has appeared in a data breach")]
+    BreachedPassword,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum TwoFAError {
+    #[error("Invalid login attempt id")]
+    InvalidLoginAttemptId,
+    #[error("Invalid 2FA code")]
+    InvalidTwoFACode,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum PasswordHashError {
+    #[error("Password does not match")]
+    Mismatch,
+    #[error("Invalid password hash")]
+    InvalidHash,
+    #[error("Unsupported password hash algorithm")]
+    UnsupportedAlgorithm,
+    #[error("Unable to hash password")]
+    UnexpectedError,
+}
+
+// Errors of the OAuth endpoints, displayed as the error codes of RFC 6749
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum OAuthError {
+    #[error("invalid_request")]
+    InvalidRequest(&'static str),
+    #[error("invalid_client")]
+    InvalidClient,
+    #[error("invalid_grant")]
+    InvalidGrant,
+    #[error("unauthorized_client")]
+    UnauthorizedClient,
+    #[error("unsupported_grant_type")]
+    UnsupportedGrantType,
+    #[error("invalid_scope")]
+    InvalidScope,
+    #[error("unsupported_response_type")]
+    UnsupportedResponseType,
+    #[error("access_denied")]
+    AccessDenied,
+    // RFC 8628, answers to a device polling `/token`
+    #[error("authorization_pending")]
+    AuthorizationPending,
+    #[error("slow_down")]
+    SlowDown,
+    #[error("expired_token")]
+    ExpiredToken,
+    #[error("server_error")]
+    ServerError,
+}
+
+impl OAuthError {
+    pub fn description(&self) -> Option<&'static str> {
+        match self {
+            OAuthError::InvalidRequest(description) => Some(description),
+            _ => None,
+        }
+    }
 }
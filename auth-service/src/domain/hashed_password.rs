@@ -0,0 +1,250 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+
+use super::{Password, PasswordHashError};
+
+// Either a PHC string (`$argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>`, `$scrypt$...`,
+// `$pbkdf2-sha256$...`) or a bcrypt modular crypt string (`$2b$12$...`).
+// Only Argon2id is ever produced, the rest is accepted so users can be imported from the old system.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HashedPassword(String);
+
+impl HashedPassword {
+    /// Hash with Argon2id on the blocking pool, it is too slow to run on the async runtime.
+    pub async fn hash(password: &Password, params: Params) -> Result<Self, PasswordHashError> {
+        let password = password.as_ref().to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password(password.as_bytes(), &salt)
+                .map(|hash| Self(hash.to_string()))
+                .map_err(|_| PasswordHashError::UnexpectedError)
+        })
+        .await
+        .map_err(|_| PasswordHashError::UnexpectedError)?
+    }
+
+    pub fn parse(hash: &str) -> Result<Self, PasswordHashError> {
+        if is_bcrypt(hash) {
+            // cost and salt are validated by the bcrypt parser on verify, check the shape here
+            let bytes = hash.as_bytes();
+            let valid =
+                bytes.len() == 60 && bytes[4..6].iter().all(u8::is_ascii_digit) && bytes[6] == b'$';
+            return valid
+                .then(|| Self(hash.to_owned()))
+                .ok_or(PasswordHashError::InvalidHash);
+        }
+
+        let phc = PasswordHash::new(hash).map_err(|_| PasswordHashError::InvalidHash)?;
+        match phc.algorithm.as_str() {
+            "argon2id" | "argon2i" | "argon2d" | "scrypt" | "pbkdf2-sha256" | "pbkdf2-sha512" => {
+                Ok(Self(hash.to_owned()))
+            }
+            _ => Err(PasswordHashError::UnsupportedAlgorithm),
+        }
+    }
+
+    /// Constant time check of `candidate` against the stored hash.
+    /// The parameters are taken from the hash itself.
+    pub async fn verify(&self, candidate: &Password) -> Result<(), PasswordHashError> {
+        let hash = self.0.clone();
+        let candidate = candidate.as_ref().to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            if is_bcrypt(&hash) {
+                return match bcrypt::verify(candidate.as_bytes(), &hash) {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err(PasswordHashError::Mismatch),
+                    Err(_) => Err(PasswordHashError::InvalidHash),
+                };
+            }
+
+            let hash = PasswordHash::new(&hash).map_err(|_| PasswordHashError::InvalidHash)?;
+            let verifiers: [&dyn PasswordVerifier; 3] = [&Argon2::default(), &Scrypt, &Pbkdf2];
+            hash.verify_password(&verifiers, candidate.as_bytes())
+                .map_err(|e| match e {
+                    argon2::password_hash::Error::Password => PasswordHashError::Mismatch,
+                    _ => PasswordHashError::InvalidHash,
+                })
+        })
+        .await
+        .map_err(|_| PasswordHashError::UnexpectedError)?
+    }
+
+    /// Whether the hash was made with anything else than Argon2id and the given parameters.
+    pub fn needs_rehash(&self, params: &Params) -> bool {
+        let Ok(hash) = PasswordHash::new(&self.0) else {
+            return true;
+        };
+        let Ok(current) = Params::try_from(&hash) else {
+            return true;
+        };
+
+        hash.algorithm != argon2::ARGON2ID_IDENT
+            || hash.version != Some(Version::V0x13.into())
+            || current.m_cost() != params.m_cost()
+            || current.t_cost() != params.t_cost()
+            || current.p_cost() != params.p_cost()
+    }
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+}
+
+impl AsRef<str> for HashedPassword {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::password_hash::PasswordHasher;
+
+    // keep the tests fast, production parameters come from the config
+    fn params() -> Params {
+        Params::new(1024, 1, 1, None).unwrap()
+    }
+
+    fn password() -> Password {
+        Password::parse("123oi1u23").unwrap()
+    }
+
+    fn wrong_password() -> Password {
+        Password::parse("123oi1u24").unwrap()
+    }
+
+    fn salt() -> SaltString {
+        SaltString::generate(&mut OsRng)
+    }
+
+    #[tokio::test]
+    async fn test_hash_is_argon2id_phc_string() {
+        let hash = HashedPassword::hash(&password(), params()).await.unwrap();
+
+        assert!(hash.as_ref().starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert_ne!(hash.as_ref(), password().as_ref());
+    }
+
+    #[tokio::test]
+    async fn test_same_password_gets_different_salt() {
+        let first = HashedPassword::hash(&password(), params()).await.unwrap();
+        let second = HashedPassword::hash(&password(), params()).await.unwrap();
+
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let hash = HashedPassword::hash(&password(), params()).await.unwrap();
+
+        assert_eq!(hash.verify(&password()).await, Ok(()));
+        assert_eq!(
+            hash.verify(&wrong_password()).await,
+            Err(PasswordHashError::Mismatch)
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_plaintext() {
+        assert_eq!(
+            HashedPassword::parse("123oi1u23"),
+            Err(PasswordHashError::InvalidHash)
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_truncated_bcrypt() {
+        assert_eq!(
+            HashedPassword::parse("$2b$12$tooshort"),
+            Err(PasswordHashError::InvalidHash)
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_algorithm() {
+        assert_eq!(
+            HashedPassword::parse("$md5$c2FsdHNhbHQ$aGFzaGhhc2hoYXNo"),
+            Err(PasswordHashError::UnsupportedAlgorithm)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_legacy_bcrypt() {
+        let legacy = bcrypt::hash(password().as_ref(), 4).unwrap();
+        let hash = HashedPassword::parse(&legacy).unwrap();
+
+        assert_eq!(hash.verify(&password()).await, Ok(()));
+        assert_eq!(
+            hash.verify(&wrong_password()).await,
+            Err(PasswordHashError::Mismatch)
+        );
+        assert!(hash.needs_rehash(&params()));
+    }
+
+    #[tokio::test]
+    async fn test_verify_legacy_scrypt() {
+        let legacy = Scrypt
+            .hash_password_customized(
+                password().as_ref().as_bytes(),
+                None,
+                None,
+                scrypt::Params::new(4, 8, 1, 32).unwrap(),
+                &salt(),
+            )
+            .unwrap()
+            .to_string();
+        let hash = HashedPassword::parse(&legacy).unwrap();
+
+        assert_eq!(hash.verify(&password()).await, Ok(()));
+        assert_eq!(
+            hash.verify(&wrong_password()).await,
+            Err(PasswordHashError::Mismatch)
+        );
+        assert!(hash.needs_rehash(&params()));
+    }
+
+    #[tokio::test]
+    async fn test_verify_legacy_pbkdf2() {
+        let legacy = Pbkdf2
+            .hash_password_customized(
+                password().as_ref().as_bytes(),
+                Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()),
+                None,
+                pbkdf2::Params {
+                    rounds: 1000,
+                    output_length: 32,
+                },
+                &salt(),
+            )
+            .unwrap()
+            .to_string();
+        assert!(legacy.starts_with("$pbkdf2-sha256$"));
+        let hash = HashedPassword::parse(&legacy).unwrap();
+
+        assert_eq!(hash.verify(&password()).await, Ok(()));
+        assert_eq!(
+            hash.verify(&wrong_password()).await,
+            Err(PasswordHashError::Mismatch)
+        );
+        assert!(hash.needs_rehash(&params()));
+    }
+
+    #[tokio::test]
+    async fn test_needs_rehash_when_params_change() {
+        let hash = HashedPassword::hash(&password(), params()).await.unwrap();
+
+        assert!(!hash.needs_rehash(&params()));
+        assert!(hash.needs_rehash(&Params::new(2048, 1, 1, None).unwrap()));
+        assert!(hash.needs_rehash(&Params::new(1024, 2, 1, None).unwrap()));
+    }
+}
@@ -1,10 +1,18 @@
 mod data_stores;
 pub(crate) mod email;
+mod email_client;
 mod errors;
+mod hashed_password;
+mod oauth;
 mod password;
+mod password_policy;
 mod user;
 pub use data_stores::*;
 pub use email::Email;
+pub use email_client::*;
 pub use errors::*;
-pub use password::Password;
+pub use hashed_password::HashedPassword;
+pub use oauth::{ClientSecretHash, CodeChallenge, OAuthClient};
+pub use password::{Password, PasswordFeedback, MIN_PASSWORD_LENGTH};
+pub use password_policy::{BreachedPasswords, PasswordPolicy, PASSWORD_MIN_LENGTH_FLOOR};
 pub use user::User;
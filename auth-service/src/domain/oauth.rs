@@ -0,0 +1,257 @@
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+
+use super::{OAuthError, PasswordHashError};
+
+// An application allowed to send users through `/authorize`, or a backend service
+// getting tokens of its own with the client credentials grant
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct OAuthClient {
+    pub client_id: String,
+    pub name: String,
+    // exact matches only, no prefixes or wildcards
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    // confidential clients only
+    #[serde(default, deserialize_with = "deserialize_secret_hash")]
+    pub client_secret_hash: Option<ClientSecretHash>,
+    // what the client may ask for, for its users or for itself
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl OAuthClient {
+    pub fn is_confidential(&self) -> bool {
+        self.client_secret_hash.is_some()
+    }
+
+    /// Checks the secret of a confidential client, a public client has none to check.
+    pub async fn authenticate(&self, client_secret: Option<&str>) -> Result<(), OAuthError> {
+        let (Some(hash), Some(secret)) = (&self.client_secret_hash, client_secret) else {
+            return if self.is_confidential() {
+                Err(OAuthError::InvalidClient)
+            } else {
+                Ok(())
+            };
+        };
+
+        hash.verify(secret)
+            .await
+            .map_err(|_| OAuthError::InvalidClient)
+    }
+
+    /// The scopes asked for, all of the client's scopes when none are.
+    /// Whatever the grant, a client never gets a scope it isn't registered for.
+    pub fn grant_scope(&self, requested: Option<&str>) -> Result<String, OAuthError> {
+        let Some(requested) = requested else {
+            return Ok(self.scopes.join(" "));
+        };
+
+        if requested
+            .split_whitespace()
+            .all(|scope| self.scopes.iter().any(|allowed| allowed == scope))
+        {
+            Ok(requested.split_whitespace().collect::<Vec<_>>().join(" "))
+        } else {
+            Err(OAuthError::InvalidScope)
+        }
+    }
+
+    pub fn allows_redirect(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris
+            .iter()
+            .any(|allowed| allowed == redirect_uri)
+    }
+}
+
+fn deserialize_secret_hash<'de, D>(deserializer: D) -> Result<Option<ClientSecretHash>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|hash| ClientSecretHash::parse(&hash).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+// Argon2id PHC string of a client secret. Secrets are generated, not picked by users,
+// so none of the password rules apply to them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientSecretHash(String);
+
+impl ClientSecretHash {
+    pub fn parse(hash: &str) -> Result<Self, PasswordHashError> {
+        let phc = PasswordHash::new(hash).map_err(|_| PasswordHashError::InvalidHash)?;
+        if phc.algorithm != argon2::ARGON2ID_IDENT {
+            return Err(PasswordHashError::UnsupportedAlgorithm);
+        }
+        Ok(Self(hash.to_owned()))
+    }
+
+    /// Constant time check of `secret`, on the blocking pool like the password checks.
+    pub async fn verify(&self, secret: &str) -> Result<(), PasswordHashError> {
+        let hash = self.0.clone();
+        let secret = secret.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let hash = PasswordHash::new(&hash).map_err(|_| PasswordHashError::InvalidHash)?;
+            Argon2::default()
+                .verify_password(secret.as_bytes(), &hash)
+                .map_err(|e| match e {
+                    argon2::password_hash::Error::Password => PasswordHashError::Mismatch,
+                    _ => PasswordHashError::InvalidHash,
+                })
+        })
+        .await
+        .map_err(|_| PasswordHashError::UnexpectedError)?
+    }
+}
+
+impl AsRef<str> for ClientSecretHash {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// PKCE `code_challenge`, only the S256 method is supported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeChallenge(String);
+
+impl CodeChallenge {
+    pub fn parse(challenge: &str, method: Option<&str>) -> Result<Self, OAuthError> {
+        if method != Some("S256") {
+            return Err(OAuthError::InvalidRequest(
+                "code_challenge_method must be S256",
+            ));
+        }
+        // BASE64URL of a SHA-256 hash
+        let is_valid = challenge.len() == 43
+            && challenge
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+
+        if !is_valid {
+            return Err(OAuthError::InvalidRequest("invalid code_challenge"));
+        }
+        Ok(Self(challenge.to_owned()))
+    }
+
+    pub fn verify(&self, code_verifier: &str) -> bool {
+        // RFC 7636: 43 to 128 unreserved characters
+        let is_valid = (43..=128).contains(&code_verifier.len())
+            && code_verifier
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-._~".contains(&b));
+
+        is_valid && URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes())) == self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+
+    // RFC 7636, appendix B
+    const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    #[test]
+    fn code_challenge_shall_verify_s256() {
+        let challenge = CodeChallenge::parse(CHALLENGE, Some("S256")).unwrap();
+
+        assert!(challenge.verify(VERIFIER));
+        assert!(!challenge.verify(&VERIFIER.replace('d', "e")));
+        assert!(!challenge.verify(CHALLENGE));
+    }
+
+    #[test]
+    fn code_challenge_shall_reject_plain_method() {
+        for method in [None, Some("plain"), Some("s256")] {
+            assert!(matches!(
+                CodeChallenge::parse(CHALLENGE, method),
+                Err(OAuthError::InvalidRequest(_))
+            ));
+        }
+        assert!(matches!(
+            CodeChallenge::parse(VERIFIER.trim_end_matches('k'), Some("S256")),
+            Err(OAuthError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn client_shall_only_allow_registered_redirects() {
+        let client = OAuthClient {
+            client_id: "spa".to_owned(),
+            name: "SPA".to_owned(),
+            redirect_uris: vec!["https://app.example.com/callback".to_owned()],
+            ..OAuthClient::default()
+        };
+
+        assert!(client.allows_redirect("https://app.example.com/callback"));
+        assert!(!client.allows_redirect("https://app.example.com/callback/"));
+        assert!(!client.allows_redirect("https://app.example.com/callback?next=evil"));
+        assert!(!client.allows_redirect("https://evil.example.com/callback"));
+    }
+
+    fn service() -> OAuthClient {
+        OAuthClient {
+            client_id: "app-service".to_owned(),
+            name: "App service".to_owned(),
+            client_secret_hash: Some(ClientSecretHash::parse(SECRET_HASH).unwrap()),
+            scopes: vec!["introspect".to_owned(), "read".to_owned()],
+            ..OAuthClient::default()
+        }
+    }
+
+    // "test-client-secret"
+    const SECRET_HASH: &str =
+        "$argon2id$v=19$m=1024,t=1,p=1$/hzmBlJTpemMFZKT+C81vQ$rowsw5bsFcKYNr6YwJOLPpxW73pIcWIrGeDdIZEZomA";
+
+    #[tokio::test]
+    async fn confidential_client_shall_authenticate_with_its_secret() {
+        let client = service();
+
+        assert_eq!(
+            client.authenticate(Some("test-client-secret")).await,
+            Ok(())
+        );
+        for secret in [None, Some("wrong-secret"), Some("")] {
+            assert_eq!(
+                client.authenticate(secret).await,
+                Err(OAuthError::InvalidClient)
+            );
+        }
+        assert_eq!(OAuthClient::default().authenticate(None).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn client_secret_shall_not_follow_the_password_rules() {
+        let hash = Argon2::default()
+            .hash_password(b"s3", &SaltString::generate(&mut OsRng))
+            .unwrap()
+            .to_string();
+        let hash = ClientSecretHash::parse(&hash).unwrap();
+
+        assert_eq!(hash.verify("s3").await, Ok(()));
+        assert_eq!(hash.verify("s4").await, Err(PasswordHashError::Mismatch));
+        // only Argon2id, there are no legacy client secrets to import
+        assert!(ClientSecretHash::parse(
+            "$2b$04$KKyZ1V7Mp4Q8XeBDmdKbbeRBHNqnMO5DOu0hvUhw7XN0EFGqV0ZWK"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn client_shall_only_be_granted_its_scopes() {
+        let client = service();
+
+        assert_eq!(client.grant_scope(None), Ok("introspect read".to_owned()));
+        assert_eq!(client.grant_scope(Some(" read ")), Ok("read".to_owned()));
+        assert_eq!(
+            client.grant_scope(Some("read admin")),
+            Err(OAuthError::InvalidScope)
+        );
+    }
+}
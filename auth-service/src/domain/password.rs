@@ -1,32 +1,28 @@
+use serde::Serialize;
 use validator::HasLen;
-// use zxcvbn::{zxcvbn, Score};
-use zxcvbn::zxcvbn;
 
 use super::CreateUserError;
 
+pub const MIN_PASSWORD_LENGTH: u64 = 8;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Password(String);
 
+// Why a new password was rejected, mostly what zxcvbn has to say about it, shown to the user as is
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PasswordFeedback {
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
 impl Password {
     pub fn parse(pas: &str) -> Result<Password, CreateUserError> {
-        if pas.length() < 8 {
-            eprintln!("password too short");
+        if pas.length() < MIN_PASSWORD_LENGTH {
             return Err(CreateUserError::InvalidPassword);
         }
 
-        let _password_strength = zxcvbn(pas, &[]);
-
-        // if password_strength.score() < Score::Two {
-        //     eprintln!("try a stronger password");
-        //     return Err(CreateUserError::InvalidPassword);
-        // }
-
         Ok(Password(pas.to_string()))
     }
-
-    // pub fn as_str(&self) -> &str {
-    //     &self.0
-    // }
 }
 
 impl AsRef<str> for Password {
@@ -34,3 +30,16 @@ impl AsRef<str> for Password {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shall_reject_short_password() {
+        assert_eq!(
+            Password::parse("1234567"),
+            Err(CreateUserError::InvalidPassword)
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    sync::Arc,
+};
+
+use sha1::{Digest, Sha1};
+use zxcvbn::{feedback::Suggestion, zxcvbn, Score};
+
+use super::{CreateUserError, Password, PasswordFeedback, MIN_PASSWORD_LENGTH};
+
+// Lowest minimal length a policy may be configured with, `Password` itself won't go below it
+pub const PASSWORD_MIN_LENGTH_FLOOR: usize = MIN_PASSWORD_LENGTH as usize;
+pub const DEFAULT_PASSWORD_MAX_LENGTH: usize = 128;
+
+// Offline list of breached or common passwords.
+// Only the first 64 bits of every SHA-1 are kept, sorted, so lookups are a binary search
+// and a million passwords take 8 MB.
+#[derive(Debug, Default, Clone)]
+pub struct BreachedPasswords {
+    prefixes: Vec<u64>,
+}
+
+impl BreachedPasswords {
+    /// Load a list with one entry per line, either a HIBP style `SHA1HEX[:count]`
+    /// or a plain text password (e.g. a common passwords list). Lines starting with `#` are skipped.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> io::Result<Self> {
+        let mut prefixes = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let hash = line.split(':').next().unwrap_or_default();
+            let prefix = if hash.len() == 40 && hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+                u64::from_str_radix(&hash[..16], 16).ok()
+            } else {
+                None
+            };
+            prefixes.push(prefix.unwrap_or_else(|| sha1_prefix(line)));
+        }
+
+        prefixes.sort_unstable();
+        prefixes.dedup();
+        Ok(Self { prefixes })
+    }
+
+    pub fn contains(&self, password: &str) -> bool {
+        self.prefixes.binary_search(&sha1_prefix(password)).is_ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.prefixes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+}
+
+fn sha1_prefix(password: &str) -> u64 {
+    let digest = Sha1::digest(password.as_bytes());
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(prefix)
+}
+
+// NIST 800-63B style rules for new passwords: length bounds, a breached passwords blocklist
+// and a zxcvbn score that takes user and service specific words into account.
+// No composition rules on purpose.
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub min_score: Score,
+    pub context_words: Vec<String>,
+    pub breached: Option<Arc<BreachedPasswords>>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: PASSWORD_MIN_LENGTH_FLOOR,
+            max_length: DEFAULT_PASSWORD_MAX_LENGTH,
+            min_score: Score::Three,
+            context_words: vec![],
+            breached: None,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Parse a password that is about to be set.
+    /// `user_inputs` are words tied to the user (email etc.) that make a password easier to guess.
+    pub fn parse(&self, pas: &str, user_inputs: &[&str]) -> Result<Password, CreateUserError> {
+        let length = pas.chars().count();
+
+        if length < self.min_length {
+            return Err(weak(format!(
+                "Password must be at least {} characters long.",
+                self.min_length
+            )));
+        }
+        if length > self.max_length {
+            return Err(weak(format!(
+                "Password must be at most {} characters long.",
+                self.max_length
+            )));
+        }
+
+        let password = Password::parse(pas).map_err(|_| {
+            weak(format!(
+                "Password must be at least {MIN_PASSWORD_LENGTH} characters long."
+            ))
+        })?;
+
+        if self
+            .breached
+            .as_ref()
+            .is_some_and(|breached| breached.contains(pas))
+        {
+            return Err(CreateUserError::BreachedPassword);
+        }
+
+        let inputs: Vec<&str> = user_inputs
+            .iter()
+            .copied()
+            .chain(self.context_words.iter().map(String::as_str))
+            .collect();
+        let password_strength = zxcvbn(pas, &inputs);
+
+        if password_strength.score() < self.min_score {
+            // zxcvbn only gives feedback below Score::Three
+            let feedback = password_strength.feedback().map_or_else(
+                || PasswordFeedback {
+                    warning: None,
+                    suggestions: vec![Suggestion::AddAnotherWordOrTwo.to_string()],
+                },
+                |feedback| PasswordFeedback {
+                    warning: feedback.warning().map(|warning| warning.to_string()),
+                    suggestions: feedback
+                        .suggestions()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect(),
+                },
+            );
+            return Err(CreateUserError::WeakPassword(feedback));
+        }
+
+        Ok(password)
+    }
+}
+
+fn weak(warning: String) -> CreateUserError {
+    CreateUserError::WeakPassword(PasswordFeedback {
+        warning: Some(warning),
+        suggestions: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breached(list: &str) -> Arc<BreachedPasswords> {
+        Arc::new(BreachedPasswords::from_reader(list.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn breached_passwords_accepts_hibp_and_plain_lines() {
+        // SHA-1 of "correct horse battery staple", HIBP ranges are upper case
+        let list = "ABF7AAD6438836DBE526AA231ABDE2D0EEF74D42:12\n\nhunter2hunter2\n";
+        let breached = breached(list);
+
+        assert_eq!(breached.len(), 2);
+        assert!(breached.contains("correct horse battery staple"));
+        assert!(breached.contains("hunter2hunter2"));
+        assert!(!breached.contains("correct horse battery stapler"));
+    }
+
+    #[test]
+    fn shall_accept_strong_password() {
+        let policy = PasswordPolicy::default();
+        assert!(policy.parse("correct horse battery staple", &[]).is_ok());
+    }
+
+    #[test]
+    fn shall_reject_breached_password() {
+        let policy = PasswordPolicy {
+            breached: Some(breached("correct horse battery staple\n")),
+            ..PasswordPolicy::default()
+        };
+
+        assert_eq!(
+            policy.parse("correct horse battery staple", &[]),
+            Err(CreateUserError::BreachedPassword)
+        );
+    }
+
+    #[test]
+    fn shall_enforce_configured_length() {
+        let policy = PasswordPolicy {
+            min_length: 12,
+            max_length: 20,
+            min_score: Score::Zero,
+            ..PasswordPolicy::default()
+        };
+
+        let Err(CreateUserError::WeakPassword(too_short)) = policy.parse("x#4Lq9!zT", &[]) else {
+            panic!("short password shall be rejected");
+        };
+        assert_eq!(
+            too_short.warning,
+            Some("Password must be at least 12 characters long.".to_owned())
+        );
+
+        let Err(CreateUserError::WeakPassword(too_long)) =
+            policy.parse("correct horse battery staple", &[])
+        else {
+            panic!("long password shall be rejected");
+        };
+        assert_eq!(
+            too_long.warning,
+            Some("Password must be at most 20 characters long.".to_owned())
+        );
+    }
+
+    #[test]
+    fn shall_count_characters_not_bytes() {
+        let policy = PasswordPolicy {
+            max_length: 10,
+            min_score: Score::Zero,
+            ..PasswordPolicy::default()
+        };
+
+        // 10 characters, 20 bytes
+        assert!(policy.parse("ññññññññññ", &[]).is_ok());
+    }
+
+    #[test]
+    fn shall_reject_common_password_with_feedback() {
+        let Err(CreateUserError::WeakPassword(feedback)) =
+            PasswordPolicy::default().parse("password123", &[])
+        else {
+            panic!("password123 shall be rejected");
+        };
+
+        assert!(feedback.warning.is_some());
+        assert!(!feedback.suggestions.is_empty());
+    }
+
+    #[test]
+    fn shall_penalise_user_inputs_and_context_words() {
+        let password = "zaphodbeeblebrox";
+        let policy = PasswordPolicy::default();
+        assert!(policy.parse(password, &[]).is_ok());
+        assert!(policy.parse(password, &["zaphod", "beeblebrox"]).is_err());
+
+        let policy = PasswordPolicy {
+            context_words: vec!["zaphod".to_owned(), "beeblebrox".to_owned()],
+            ..PasswordPolicy::default()
+        };
+        assert!(policy.parse(password, &[]).is_err());
+    }
+}
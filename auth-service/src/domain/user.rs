@@ -1,25 +1,18 @@
-use super::{CreateUserError, Email, Password};
+use super::{Email, HashedPassword};
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct User {
     pub email: Email,
-    pub password: Password,
+    pub password: HashedPassword,
     pub requires_2fa: bool,
 }
 
 impl User {
-    pub fn new(email: &str, password: &str, requires2fa: bool) -> Result<User, CreateUserError> {
-        let email = Email::parse(email)?;
-        let password = Password::parse(password)?;
-        let requires_2fa = match Some(requires2fa) {
-            Some(val) => val,
-            None => true,
-        };
-
-        Ok(User {
+    pub fn new(email: Email, password: HashedPassword, requires_2fa: bool) -> User {
+        User {
             email,
             password,
             requires_2fa,
-        })
+        }
     }
 }
@@ -1,14 +1,17 @@
 use app_state::AppState;
 use axum::{
-    http::{Method, StatusCode},
+    http::{header, Method, StatusCode},
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{get, get_service, post},
     serve::Serve,
     Json, Router,
 };
-use domain::AuthAPIError;
+use domain::{AuthAPIError, OAuthError};
 use serde::{Deserialize, Serialize};
-use tower_http::{cors::CorsLayer, services::ServeDir};
+use tower_http::{
+    cors::CorsLayer,
+    services::{ServeDir, ServeFile},
+};
 
 pub mod app_state;
 pub mod domain;
@@ -42,8 +45,30 @@ impl Application {
             .route("/signup", post(routes::signup))
             .route("/login", post(routes::login))
             .route("/logout", post(routes::logout))
+            .route("/refresh", post(routes::refresh))
             .route("/verify-token", post(routes::verify_token))
             .route("/verify-2fa", post(routes::verify_2fa))
+            .route("/change-password", post(routes::change_password))
+            .route("/forgot-password", post(routes::forgot_password))
+            .route("/reset-password", post(routes::reset_password))
+            .route("/authorize", get(routes::authorize).post(routes::consent))
+            .route("/clients/:client_id", get(routes::client_info))
+            .route("/token", post(routes::token))
+            .route("/introspect", post(routes::introspect))
+            .route("/revoke", post(routes::revoke))
+            .route("/device_authorization", post(routes::device_authorization))
+            .route("/device", get_service(ServeFile::new("assets/device.html")))
+            .route(
+                "/device/verify",
+                get(routes::verify_device).post(routes::answer_device),
+            )
+            .route("/device/request", get(routes::device_request))
+            .route("/userinfo", get(routes::userinfo))
+            .route("/.well-known/jwks.json", get(routes::jwks))
+            .route(
+                "/.well-known/openid-configuration",
+                get(routes::openid_configuration),
+            )
             .route("/hello", get(routes::hello_handler))
             .with_state(app_state)
             .layer(cors);
@@ -66,6 +91,45 @@ pub struct ErrorResponse {
     pub error: String,
 }
 
+// Error body for a rejected new password, carries zxcvbn's feedback for the UI
+#[derive(Serialize, Deserialize)]
+pub struct WeakPasswordResponse {
+    pub error: String,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+// RFC 6749 error body of the OAuth endpoints
+#[derive(Serialize, Deserialize)]
+pub struct OAuthErrorResponse {
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            OAuthError::InvalidClient => StatusCode::UNAUTHORIZED, // 401
+            OAuthError::ServerError => StatusCode::INTERNAL_SERVER_ERROR, // 500
+            _ => StatusCode::BAD_REQUEST,                          // 400
+        };
+
+        let body = Json(OAuthErrorResponse {
+            error: self.to_string(),
+            error_description: self.description().map(str::to_owned),
+        });
+        let mut response = (status, [(header::CACHE_CONTROL, "no-store")], body).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Basic"),
+            );
+        }
+        response
+    }
+}
+
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
@@ -85,6 +149,18 @@ impl IntoResponse for AuthAPIError {
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),       // 401
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing token"),        // 400
             AuthAPIError::MalformedToken => (StatusCode::UNPROCESSABLE_ENTITY, "Malformed token"), // 422
+            AuthAPIError::BreachedPassword => (
+                StatusCode::BAD_REQUEST,
+                "Password has appeared in a data breach, please choose another one",
+            ), // 400
+            AuthAPIError::WeakPassword(feedback) => {
+                let body = Json(WeakPasswordResponse {
+                    error: "Password is too weak".to_string(),
+                    warning: feedback.warning,
+                    suggestions: feedback.suggestions,
+                });
+                return (StatusCode::BAD_REQUEST, body).into_response(); // 400
+            }
         };
 
         let body = Json(ErrorResponse {
@@ -1,18 +1,80 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use auth_service::{
-    app_state::AppState,
-    services::{HashmapUserStore, HashsetBannedTokenStore},
-    utils::constants::prod,
+    app_state::{
+        AppState, AuthorizationCodeStoreType, BannedTokensType, ClientStoreType,
+        DeviceCodeStoreType, EmailClientType, KeyringType, PasswordResetTokenStoreType,
+        RefreshTokenStoreType, TwoFACodeStoreType, UserStoreType,
+    },
+    services::{
+        FileEmailClient, HashmapAuthorizationCodeStore, HashmapClientStore, HashmapDeviceCodeStore,
+        HashmapPasswordResetTokenStore, HashmapRefreshTokenStore, HashmapTwoFACodeStore,
+        HashsetBannedTokenStore, PostgresBannedTokenStore, PostgresConfig, PostgresUserStore,
+        RedisBannedTokenStore, SmtpConfig, SmtpEmailClient, SqliteUserStore,
+    },
+    utils::{
+        constants::{
+            prod, BANNED_TOKEN_PRUNE_INTERVAL_SECONDS, DATABASE_MAX_CONNECTIONS, DATABASE_URL,
+            EMAIL_OUTBOX_DIR, EMAIL_SENDER, OAUTH_CLIENTS_PATH, PASSWORD_POLICY, REDIS_URL,
+            REFRESH_TOKEN_PRUNE_INTERVAL_SECONDS,
+        },
+        keyring::Keyring,
+    },
     Application,
 };
-use tokio::sync::RwLock;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::RwLock,
+};
 
 #[tokio::main]
 async fn main() {
-    let user_store = Arc::new(RwLock::new(HashmapUserStore::default()));
-    let banned_tokens = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-    let app_state = AppState::new(user_store, banned_tokens);
+    let (user_store, banned_tokens) = build_stores().await;
+    let two_fa_code_store: TwoFACodeStoreType =
+        Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+    let password_reset_token_store: PasswordResetTokenStoreType =
+        Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default()));
+    let refresh_tokens = HashmapRefreshTokenStore::default();
+    // runs for the lifetime of the process
+    let _pruning =
+        refresh_tokens.spawn_pruning(Duration::from_secs(REFRESH_TOKEN_PRUNE_INTERVAL_SECONDS));
+    let refresh_token_store: RefreshTokenStoreType = Arc::new(RwLock::new(refresh_tokens));
+    let client_store: ClientStoreType = Arc::new(RwLock::new(match OAUTH_CLIENTS_PATH.as_ref() {
+        Some(path) => HashmapClientStore::load(path).expect("Failed to load OAuth clients"),
+        None => HashmapClientStore::default(),
+    }));
+    let authorization_code_store: AuthorizationCodeStoreType =
+        Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default()));
+    let device_code_store: DeviceCodeStoreType =
+        Arc::new(RwLock::new(HashmapDeviceCodeStore::default()));
+    let email_client: EmailClientType = match SmtpConfig::from_env() {
+        Some(config) => Arc::new(
+            SmtpEmailClient::new(config, EMAIL_SENDER.as_str())
+                .expect("Failed to configure SMTP client"),
+        ),
+        None => Arc::new(FileEmailClient::new(
+            EMAIL_OUTBOX_DIR.as_str(),
+            EMAIL_SENDER.as_str(),
+        )),
+    };
+    let password_policy = Arc::new(PASSWORD_POLICY.clone());
+    let keyring: KeyringType = Arc::new(RwLock::new(
+        Keyring::from_env().expect("Failed to load signing keys"),
+    ));
+    reload_keyring_on_sighup(keyring.clone());
+    let app_state = AppState::new(
+        user_store,
+        banned_tokens,
+        two_fa_code_store,
+        password_reset_token_store,
+        refresh_token_store,
+        client_store,
+        authorization_code_store,
+        device_code_store,
+        email_client,
+        password_policy,
+        keyring,
+    );
 
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
@@ -20,3 +82,61 @@ async fn main() {
 
     app.run().await.expect("failed to run app");
 }
+
+// `kill -HUP` picks up a rotated keyring without a restart, a broken one leaves the keys as they are
+fn reload_keyring_on_sighup(keyring: KeyringType) {
+    let mut hangups = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match Keyring::from_env() {
+                Ok(reloaded) => {
+                    *keyring.write().await = reloaded;
+                    eprintln!("signing keys reloaded");
+                }
+                Err(e) => eprintln!("unable to reload signing keys: {e}"),
+            }
+        }
+    });
+}
+
+// Postgres when DATABASE_URL points to it, a local SQLite file otherwise.
+// Banned tokens prefer a RESP server (REDIS_URL) so replicas share them and they expire by themselves.
+async fn build_stores() -> (UserStoreType, BannedTokensType) {
+    let postgres = match PostgresConfig::from_env() {
+        Some(config) => Some(
+            config
+                .connect()
+                .await
+                .expect("Failed to connect to Postgres"),
+        ),
+        None => None,
+    };
+
+    let user_store: UserStoreType = match &postgres {
+        Some(pool) => Arc::new(RwLock::new(PostgresUserStore::new(pool.clone()))),
+        None => Arc::new(RwLock::new(
+            SqliteUserStore::connect(&DATABASE_URL, *DATABASE_MAX_CONNECTIONS)
+                .await
+                .expect("Failed to open user database"),
+        )),
+    };
+
+    let banned_tokens: BannedTokensType = match (REDIS_URL.as_deref(), postgres) {
+        (Some(url), _) => Arc::new(RwLock::new(
+            RedisBannedTokenStore::connect(url)
+                .await
+                .expect("Failed to connect to Redis"),
+        )),
+        (None, Some(pool)) => Arc::new(RwLock::new(PostgresBannedTokenStore::new(pool))),
+        (None, None) => {
+            let store = HashsetBannedTokenStore::default();
+            // runs for the lifetime of the process
+            let _pruning =
+                store.spawn_pruning(Duration::from_secs(*BANNED_TOKEN_PRUNE_INTERVAL_SECONDS));
+            Arc::new(RwLock::new(store))
+        }
+    };
+
+    (user_store, banned_tokens)
+}
@@ -0,0 +1,220 @@
+use axum::{
+    extract::{Path, Query, RawQuery, State},
+    response::Redirect,
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use url::{form_urlencoded, Url};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        Authentication, AuthorizationCode, AuthorizationGrant, ClientStoreError, CodeChallenge,
+        Email, OAuthClient, OAuthError,
+    },
+    utils::{auth::Claims, keyring::Keyring},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct AuthorizeRequest {
+    pub response_type: Option<String>,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ConsentRequest {
+    #[serde(flatten)]
+    pub request: AuthorizeRequest,
+    pub approved: bool,
+}
+
+// What the consent page shows about a client, never taken from its own URL
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClientResponse {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConsentResponse {
+    #[serde(rename = "redirectTo")]
+    pub redirect_to: String,
+}
+
+// Start of the authorization code flow, the login UI takes over to log the user in and ask for consent
+pub async fn authorize(
+    State(state): State<AppState>,
+    RawQuery(query): RawQuery,
+    Query(request): Query<AuthorizeRequest>,
+) -> Result<Redirect, OAuthError> {
+    let client = registered_client(&state, &request).await?;
+    let keyring = state.keyring.read().await;
+
+    if let Err(e) =
+        code_challenge(&request).and_then(|_| granted_scope(&client, &request, &keyring))
+    {
+        return Ok(Redirect::to(&error_redirect(&request, &e)?));
+    }
+
+    let consent_page = form_urlencoded::Serializer::new(String::new())
+        .append_pair("oauth_request", &query.unwrap_or_default())
+        .finish();
+    Ok(Redirect::to(&format!("/?{consent_page}")))
+}
+
+// Name of a registered client, for the consent page to show who is asking
+pub async fn client_info(
+    State(state): State<AppState>,
+    Path(client_id): Path<String>,
+) -> Result<Json<ClientResponse>, OAuthError> {
+    let client = state
+        .client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+        .map_err(|e| match e {
+            ClientStoreError::ClientNotFound => OAuthError::InvalidRequest("unknown client_id"),
+            _ => OAuthError::ServerError,
+        })?;
+
+    Ok(Json(ClientResponse {
+        client_id: client.client_id,
+        name: client.name,
+    }))
+}
+
+// Answer of the logged in user on the consent page, the UI then follows `redirectTo` back to the client
+pub async fn consent(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(consent): Json<ConsentRequest>,
+) -> Result<Json<ConsentResponse>, OAuthError> {
+    let request = consent.request;
+    let client = registered_client(&state, &request).await?;
+
+    let checked = {
+        let keyring = state.keyring.read().await;
+        code_challenge(&request)
+            .and_then(|challenge| Ok((challenge, granted_scope(&client, &request, &keyring)?)))
+    };
+    let (code_challenge, scope) = match checked {
+        Ok(checked) if consent.approved => checked,
+        Ok(_) => return redirect_to(error_redirect(&request, &OAuthError::AccessDenied)?),
+        Err(e) => return redirect_to(error_redirect(&request, &e)?),
+    };
+
+    let email = Email::parse(&claims.sub).map_err(|_| OAuthError::ServerError)?;
+    let code = AuthorizationCode::default();
+    state
+        .authorization_code_store
+        .write()
+        .await
+        .add_code(
+            &code,
+            AuthorizationGrant {
+                client_id: request.client_id.clone(),
+                redirect_uri: request.redirect_uri.clone(),
+                email,
+                code_challenge,
+                scope,
+                nonce: request.nonce.clone(),
+                authentication: Authentication {
+                    auth_time: claims.auth_time.unwrap_or(claims.iat),
+                    amr: claims.amr,
+                },
+            },
+        )
+        .await
+        .map_err(|_| OAuthError::ServerError)?;
+
+    redirect_to(redirect_uri(&request, &[("code", code.as_ref())])?)
+}
+
+// Checked first: until the redirect URI is known to belong to the client, errors are never sent to it
+async fn registered_client(
+    state: &AppState,
+    request: &AuthorizeRequest,
+) -> Result<OAuthClient, OAuthError> {
+    let client = state
+        .client_store
+        .read()
+        .await
+        .get_client(&request.client_id)
+        .await
+        .map_err(|e| match e {
+            ClientStoreError::ClientNotFound => OAuthError::InvalidRequest("unknown client_id"),
+            _ => OAuthError::ServerError,
+        })?;
+
+    if !client.allows_redirect(&request.redirect_uri) {
+        return Err(OAuthError::InvalidRequest(
+            "redirect_uri is not registered for this client",
+        ));
+    }
+    Ok(client)
+}
+
+// Only the code flow is supported, and only with PKCE
+fn code_challenge(request: &AuthorizeRequest) -> Result<CodeChallenge, OAuthError> {
+    if request.response_type.as_deref() != Some("code") {
+        return Err(OAuthError::UnsupportedResponseType);
+    }
+
+    CodeChallenge::parse(
+        request.code_challenge.as_deref().unwrap_or_default(),
+        request.code_challenge_method.as_deref(),
+    )
+}
+
+// Only scopes registered for the client, all of them when none are asked for
+fn granted_scope(
+    client: &OAuthClient,
+    request: &AuthorizeRequest,
+    keyring: &Keyring,
+) -> Result<Option<String>, OAuthError> {
+    let scope = client.grant_scope(request.scope.as_deref())?;
+
+    // an ID token signed with the shared secret can't be verified by the client
+    let openid = scope.split_whitespace().any(|scope| scope == "openid");
+    if openid && !keyring.publishes_active_key(Utc::now()) {
+        return Err(OAuthError::InvalidScope);
+    }
+    Ok(Some(scope).filter(|scope| !scope.is_empty()))
+}
+
+fn error_redirect(request: &AuthorizeRequest, error: &OAuthError) -> Result<String, OAuthError> {
+    let code = error.to_string();
+    let mut params = vec![("error", code.as_str())];
+    if let Some(description) = error.description() {
+        params.push(("error_description", description));
+    }
+    redirect_uri(request, &params)
+}
+
+// The client's redirect URI with `params` and its `state` appended
+fn redirect_uri(request: &AuthorizeRequest, params: &[(&str, &str)]) -> Result<String, OAuthError> {
+    let mut url = Url::parse(&request.redirect_uri)
+        .map_err(|_| OAuthError::InvalidRequest("invalid redirect_uri"))?;
+
+    {
+        let mut query = url.query_pairs_mut();
+        query.extend_pairs(params);
+        if let Some(state) = &request.state {
+            query.append_pair("state", state);
+        }
+    }
+    Ok(url.into())
+}
+
+fn redirect_to(redirect_to: String) -> Result<Json<ConsentResponse>, OAuthError> {
+    Ok(Json(ConsentResponse { redirect_to }))
+}
@@ -0,0 +1,97 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Authentication, CreateUserError, Email, HashedPassword, Password,
+        UserStoreError,
+    },
+    routes::start_session,
+    utils::{auth::Claims, constants::ARGON2_PARAMS},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+pub async fn change_password(
+    State(state): State<AppState>,
+    claims: Claims,
+    jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let email = Email::parse(&claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let current_password = Password::parse(&request.current_password)
+        .map_err(|_| AuthAPIError::InvalidUserCredentials)?;
+    let new_password = state
+        .password_policy
+        .parse(&request.new_password, &email.password_context())
+        .map_err(|e| match e {
+            CreateUserError::WeakPassword(feedback) => AuthAPIError::WeakPassword(feedback),
+            CreateUserError::BreachedPassword => AuthAPIError::BreachedPassword,
+            _ => AuthAPIError::InvalidUserCredentials,
+        })?;
+
+    state
+        .user_store
+        .read()
+        .await
+        .validate_user(email.as_ref(), current_password.as_ref())
+        .await
+        .map_err(|e| match e {
+            UserStoreError::PasswordHash(_) => AuthAPIError::UnexpectedError,
+            _ => AuthAPIError::Unauthorized,
+        })?;
+
+    let hash = HashedPassword::hash(&new_password, ARGON2_PARAMS.clone())
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .update_password(email.as_ref(), hash)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    // Every token issued up to this second is revoked, the caller continues with a fresh one
+    // dated after it
+    let issued_before =
+        usize::try_from(Utc::now().timestamp() + 1).map_err(|_| AuthAPIError::UnexpectedError)?;
+    state
+        .banned_tokens
+        .write()
+        .await
+        .revoke_issued_before(email.as_ref().to_owned(), issued_before)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_all(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    // the same login goes on, with its original `auth_time` and `amr`
+    let authentication = match claims.auth_time {
+        Some(auth_time) => Authentication {
+            auth_time,
+            amr: claims.amr,
+        },
+        None => Authentication::now(false),
+    };
+    let jar = start_session(&state, &email, authentication, jar).await?;
+
+    Ok((jar, StatusCode::OK))
+}
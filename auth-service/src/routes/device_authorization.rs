@@ -0,0 +1,204 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect},
+    Form, Json,
+};
+use serde::{Deserialize, Serialize};
+use url::form_urlencoded;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        Authentication, ClientStoreError, DeviceCode, DeviceCodeStoreError, DeviceRequest, Email,
+        OAuthError, UserCode,
+    },
+    utils::{
+        auth::Claims,
+        constants::{APP_URL, DEVICE_CODE_POLL_INTERVAL_SECONDS, DEVICE_CODE_TTL_SECONDS},
+    },
+};
+
+use super::token::authenticate_client;
+
+#[derive(Deserialize, Debug)]
+pub struct DeviceAuthorizationRequest {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    pub interval: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VerifyDeviceQuery {
+    pub user_code: Option<String>,
+}
+
+// The request a user code belongs to, as the consent page shows it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PendingDeviceResponse {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub name: String,
+    pub scope: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeviceAnswerRequest {
+    #[serde(rename = "userCode")]
+    pub user_code: String,
+    pub approved: bool,
+}
+
+// Start of the device flow: the device shows the user code and polls `/token` with the device code
+pub async fn device_authorization(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<DeviceAuthorizationRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client = authenticate_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    let scope = client.grant_scope(request.scope.as_deref())?;
+
+    let device_code = DeviceCode::default();
+    let user_code = UserCode::default();
+    state
+        .device_code_store
+        .write()
+        .await
+        .add_request(
+            &device_code,
+            &user_code,
+            DeviceRequest {
+                client_id: client.client_id,
+                scope: Some(scope).filter(|scope| !scope.is_empty()),
+            },
+        )
+        .await
+        .map_err(|_| OAuthError::ServerError)?;
+
+    let verification_uri = format!("{}/device", APP_URL.trim_end_matches('/'));
+    let verification_uri_complete = format!("{verification_uri}?user_code={}", user_code.as_ref());
+    Ok((
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(DeviceAuthorizationResponse {
+            device_code: device_code.as_ref().to_owned(),
+            user_code: user_code.as_ref().to_owned(),
+            verification_uri,
+            verification_uri_complete,
+            expires_in: DEVICE_CODE_TTL_SECONDS,
+            interval: DEVICE_CODE_POLL_INTERVAL_SECONDS,
+        }),
+    ))
+}
+
+// The verification page submits the user code here, the login UI then takes over like for `/authorize`
+pub async fn verify_device(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyDeviceQuery>,
+) -> Redirect {
+    let Some(user_code) = query
+        .user_code
+        .and_then(|user_code| UserCode::parse(&user_code).ok())
+    else {
+        return Redirect::to("/device?error=invalid_request");
+    };
+
+    if let Err(error) = pending_device(&state, &user_code).await {
+        return Redirect::to(&format!("/device?error={error}"));
+    }
+
+    let consent_page = form_urlencoded::Serializer::new(String::new())
+        .append_pair("device_user_code", user_code.as_ref())
+        .finish();
+    Redirect::to(&format!("/?{consent_page}"))
+}
+
+// Looked up by the consent page, which shows the client the server has on record for the user code
+pub async fn device_request(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyDeviceQuery>,
+) -> Result<Json<PendingDeviceResponse>, OAuthError> {
+    let user_code = query
+        .user_code
+        .and_then(|user_code| UserCode::parse(&user_code).ok())
+        .ok_or(OAuthError::InvalidRequest("invalid user code"))?;
+
+    pending_device(&state, &user_code).await.map(Json)
+}
+
+// Answer of the logged in user, the device gets its token the next time it polls
+pub async fn answer_device(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(answer): Json<DeviceAnswerRequest>,
+) -> Result<StatusCode, OAuthError> {
+    let user_code = UserCode::parse(&answer.user_code)
+        .map_err(|_| OAuthError::InvalidRequest("invalid user code"))?;
+    let mut store = state.device_code_store.write().await;
+
+    let answered = if answer.approved {
+        let email = Email::parse(&claims.sub).map_err(|_| OAuthError::ServerError)?;
+        let authentication = Authentication {
+            auth_time: claims.auth_time.unwrap_or(claims.iat),
+            amr: claims.amr,
+        };
+        store.approve(&user_code, email, authentication).await
+    } else {
+        store.deny(&user_code).await
+    };
+
+    answered.map_err(device_error)?;
+    Ok(StatusCode::OK)
+}
+
+async fn pending_device(
+    state: &AppState,
+    user_code: &UserCode,
+) -> Result<PendingDeviceResponse, OAuthError> {
+    let request = state
+        .device_code_store
+        .read()
+        .await
+        .get_request(user_code)
+        .await
+        .map_err(device_error)?;
+
+    let client = state
+        .client_store
+        .read()
+        .await
+        .get_client(&request.client_id)
+        .await
+        .map_err(|e| match e {
+            ClientStoreError::ClientNotFound => OAuthError::InvalidRequest("unknown client_id"),
+            _ => OAuthError::ServerError,
+        })?;
+    Ok(PendingDeviceResponse {
+        client_id: client.client_id,
+        name: client.name,
+        scope: request.scope,
+    })
+}
+
+fn device_error(error: DeviceCodeStoreError) -> OAuthError {
+    match error {
+        DeviceCodeStoreError::Expired => OAuthError::ExpiredToken,
+        DeviceCodeStoreError::UnexpectedError => OAuthError::ServerError,
+        _ => OAuthError::InvalidRequest("unknown user code"),
+    }
+}
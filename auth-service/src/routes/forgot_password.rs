@@ -0,0 +1,72 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, PasswordResetToken, UserStoreError},
+    utils::{
+        constants::{APP_URL, PASSWORD_RESET_TOKEN_TTL_SECONDS},
+        email::password_reset_email,
+    },
+};
+
+#[derive(Deserialize, Debug)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ForgotPasswordResponse {
+    pub message: String,
+}
+
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(&request.email).map_err(|_| AuthAPIError::InvalidUserCredentials)?;
+
+    // The answer must not tell whether the account exists, neither by its content nor by how long
+    // it takes, so the link is sent in the background and failures are only logged
+    tokio::spawn(async move {
+        if let Err(e) = send_reset_link(&state, email.clone()).await {
+            eprintln!(
+                "unable to send password reset link to {}: {e}",
+                email.as_ref()
+            );
+        }
+    });
+
+    let response = Json(ForgotPasswordResponse {
+        message: "If an account exists for this email, a reset link has been sent.".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+async fn send_reset_link(state: &AppState, email: Email) -> Result<(), String> {
+    match state.user_store.read().await.get_user(email.as_ref()).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => return Ok(()),
+        Err(e) => return Err(e.to_string()),
+    }
+
+    let token = PasswordResetToken::default();
+    let link = format!("{}/?reset_token={}", APP_URL.as_str(), token.as_ref());
+    let message = password_reset_email(&link, PASSWORD_RESET_TOKEN_TTL_SECONDS / 60)
+        .map_err(|e| e.to_string())?;
+
+    state
+        .password_reset_token_store
+        .write()
+        .await
+        .add_token(email.clone(), &token)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    state
+        .email_client
+        .send_email(&email, &message)
+        .await
+        .map_err(|e| e.to_string())
+}
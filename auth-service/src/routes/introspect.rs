@@ -0,0 +1,71 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::IntoResponse,
+    Form, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::OAuthError,
+    utils::auth::{validate_token, Claims},
+};
+
+use super::token::authenticate_client;
+
+#[derive(Deserialize, Debug)]
+pub struct IntrospectionRequest {
+    pub token: Option<String>,
+    // only JWTs can be introspected, the hint changes nothing
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+// RFC 7662: `{"active": false}` alone for a token that can't be used, whatever the reason
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    // the token's own claims: sub, exp, iat, scope, client_id...
+    #[serde(flatten)]
+    pub claims: Option<Claims>,
+}
+
+// Lets resource servers check an access token and read its claims, reserved to confidential
+// clients registered with the `introspect` scope
+pub async fn introspect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<IntrospectionRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client = authenticate_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    if !client.is_confidential() || client.grant_scope(Some("introspect")).is_err() {
+        return Err(OAuthError::UnauthorizedClient);
+    }
+    let token = request
+        .token
+        .ok_or(OAuthError::InvalidRequest("token is required"))?;
+
+    let banned = state.banned_tokens.read().await;
+    let claims = validate_token(&token, &*state.keyring.read().await, &*banned)
+        .await
+        .ok();
+
+    Ok((
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(IntrospectionResponse {
+            active: claims.is_some(),
+            token_type: claims.as_ref().map(|_| "Bearer".to_owned()),
+            claims,
+        }),
+    ))
+}
@@ -0,0 +1,12 @@
+use axum::{extract::State, http::header, response::IntoResponse, Json};
+use chrono::Utc;
+
+use crate::app_state::AppState;
+
+// Public keys other services verify our JWTs with, empty while signing with a shared secret.
+// Upcoming keys are listed ahead of their activation so caches pick them up in time.
+pub async fn jwks(State(state): State<AppState>) -> impl IntoResponse {
+    let jwks = state.keyring.read().await.jwks(Utc::now());
+
+    ([(header::CACHE_CONTROL, "public, max-age=300")], Json(jwks))
+}
@@ -1,10 +1,22 @@
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, Password, UserStore},
-    // domain::{AuthAPIError, CreateUserError, Email, Password, User, UserStore, UserStoreError},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuthAPIError, Authentication, Email, HashedPassword, LoginAttemptId, Password, TwoFACode,
+        User, UserStoreError,
+    },
+    // domain::{AuthAPIError, CreateUserError, Email, Password, User, UserStoreError},
+    routes::start_session,
+    utils::{
+        constants::{ARGON2_PARAMS, TWO_FA_CODE_TTL_SECONDS},
+        email::two_fa_code_email,
+    },
+};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
 };
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
 use serde::{Deserialize, Serialize};
 
@@ -14,9 +26,11 @@ pub struct LoginRequest {
     pub password: String,
 }
 
-#[derive(Debug, Serialize)]
-pub struct LoginResponse {
-    pub email: String,
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TwoFactorAuthResponse {
+    pub message: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
 }
 
 // #[axum::debug_handler]
@@ -40,25 +54,79 @@ pub async fn login(
     })?;
     let password = Password::parse(&password).map_err(|_| AuthAPIError::InvalidUserCredentials)?;
 
-    let db = _state.user_store.read().await;
+    let user = {
+        let db = _state.user_store.read().await;
 
-    if db
-        .validate_user(email.as_ref(), password.as_ref())
-        .await
-        .is_err()
-    {
-        return Err(AuthAPIError::Unauthorized);
+        db.validate_user(email.as_ref(), password.as_ref())
+            .await
+            .map_err(|e| match e {
+                UserStoreError::PasswordHash(_) => AuthAPIError::UnexpectedError,
+                _ => AuthAPIError::Unauthorized,
+            })?;
+
+        db.get_user(email.as_ref())
+            .await
+            .map_err(|_| AuthAPIError::Unauthorized)?
+    };
+
+    if user.password.needs_rehash(&ARGON2_PARAMS) {
+        rehash_password(&_state, &user, &password).await;
+    }
+
+    if user.requires_2fa {
+        let response = handle_2fa(&user.email, &_state).await?;
+        return Ok((jar, response));
+    }
+
+    let jar = start_session(&_state, &user.email, Authentication::now(false), jar).await?;
+
+    Ok((jar, StatusCode::OK.into_response()))
+}
+
+// Upgrade legacy or outdated hashes while we know the plaintext, this never fails the login
+async fn rehash_password(state: &AppState, user: &User, password: &Password) {
+    let result = match HashedPassword::hash(password, ARGON2_PARAMS.clone()).await {
+        Ok(hash) => state
+            .user_store
+            .write()
+            .await
+            .update_password(user.email.as_ref(), hash)
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
     };
 
-    let user = db
-        .get_user(email.as_ref())
+    if let Err(e) = result {
+        eprintln!("unable to rehash password of {}: {e}", user.email.as_ref());
+    }
+}
+
+// Start a pending 2FA login: the JWT cookie is only issued by /verify-2fa
+async fn handle_2fa(email: &Email, state: &AppState) -> Result<Response, AuthAPIError> {
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+
+    state
+        .two_fa_code_store
+        .write()
+        .await
+        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
         .await
-        .map_err(|_| AuthAPIError::Unauthorized)?;
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
 
-    let auth_cookie =
-        generate_auth_cookie(&user.email).map_err(|_| AuthAPIError::UnexpectedError)?;
+    let message = two_fa_code_email(&code, TWO_FA_CODE_TTL_SECONDS / 60)
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    state
+        .email_client
+        .send_email(email, &message)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
 
-    let authorized = &jar.add(auth_cookie);
+    let response = Json(TwoFactorAuthResponse {
+        message: "2FA required".to_owned(),
+        login_attempt_id: login_attempt_id.as_ref().to_owned(),
+    });
 
-    Ok((authorized.clone(), StatusCode::OK.into_response()))
+    Ok((StatusCode::PARTIAL_CONTENT, response).into_response())
 }
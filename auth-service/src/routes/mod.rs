@@ -1,14 +1,36 @@
+mod authorize;
+mod change_password;
+mod device_authorization;
+mod forgot_password;
 mod hello;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod oidc;
+mod refresh;
+mod reset_password;
+mod revoke;
 mod signup;
+mod token;
 mod verify_2fa;
 mod verify_token;
 
 // re-export
+pub use authorize::*;
+pub use change_password::*;
+pub use device_authorization::*;
+pub use forgot_password::*;
 pub use hello::*;
+pub use introspect::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use oidc::*;
+pub use refresh::*;
+pub use reset_password::*;
+pub use revoke::*;
 pub use signup::*;
+pub use token::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
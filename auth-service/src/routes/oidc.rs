@@ -0,0 +1,47 @@
+use axum::{extract::State, http::header, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{auth::AccessTokenClaims, oidc::OpenIdConfiguration},
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    pub email: String,
+}
+
+pub async fn openid_configuration(State(state): State<AppState>) -> impl IntoResponse {
+    let configuration = OpenIdConfiguration::new(&*state.keyring.read().await);
+
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(configuration),
+    )
+}
+
+// Claims about the owner of the access token, read from the user store.
+// Only for access tokens granted the `openid` scope, not for login sessions or machine tokens
+pub async fn userinfo(
+    State(state): State<AppState>,
+    AccessTokenClaims(claims): AccessTokenClaims,
+) -> Result<Json<UserInfoResponse>, AuthAPIError> {
+    if !claims.has_scope("openid") {
+        return Err(AuthAPIError::Unauthorized);
+    }
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&claims.sub)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    Ok(Json(UserInfoResponse {
+        sub: claims.sub,
+        email: user.email.as_ref().to_owned(),
+    }))
+}
@@ -0,0 +1,107 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Authentication, Email, RefreshToken, RefreshTokenStoreError},
+    utils::{
+        auth::{create_refresh_cookie, generate_auth_cookie, session_issued_at},
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
+};
+
+// Trade the refresh token cookie for a new JWT, the refresh token is rotated on every use
+pub async fn refresh(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let cookie = jar
+        .get(REFRESH_TOKEN_COOKIE_NAME)
+        .ok_or(AuthAPIError::MissingToken)?;
+    let token = RefreshToken::parse(cookie.value()).map_err(|_| AuthAPIError::InvalidToken)?;
+    let new_token = RefreshToken::default();
+
+    let (email, authentication) = state
+        .refresh_token_store
+        .write()
+        .await
+        .rotate_token(&token, None, &new_token)
+        .await
+        .map_err(|e| match e {
+            RefreshTokenStoreError::UnexpectedError => AuthAPIError::UnexpectedError,
+            RefreshTokenStoreError::Reused => {
+                eprintln!("refresh token reused, revoked the session family");
+                AuthAPIError::InvalidToken
+            }
+            _ => AuthAPIError::InvalidToken,
+        })?;
+
+    let issued_at = session_issued_at(email.as_ref(), &*state.banned_tokens.read().await)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    let auth_cookie = generate_auth_cookie(
+        &email,
+        &authentication,
+        issued_at,
+        &*state.keyring.read().await,
+    )
+    .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok((
+        jar.add(auth_cookie).add(create_refresh_cookie(&new_token)),
+        StatusCode::OK,
+    ))
+}
+
+// Log `email` in: a JWT cookie plus a refresh token cookie starting a new token family
+pub(crate) async fn start_session(
+    state: &AppState,
+    email: &Email,
+    authentication: Authentication,
+    jar: CookieJar,
+) -> Result<CookieJar, AuthAPIError> {
+    let issued_at = session_issued_at(email.as_ref(), &*state.banned_tokens.read().await)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    let auth_cookie = generate_auth_cookie(
+        email,
+        &authentication,
+        issued_at,
+        &*state.keyring.read().await,
+    )
+    .map_err(|_| AuthAPIError::UnexpectedError)?;
+    let refresh_token = RefreshToken::default();
+
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .add_token(email.clone(), authentication, None, &refresh_token)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(jar
+        .add(auth_cookie)
+        .add(create_refresh_cookie(&refresh_token)))
+}
+
+// Revoke the refresh token family of this browser, if any, and drop both cookies
+pub(crate) async fn end_session(state: &AppState, jar: CookieJar) -> CookieJar {
+    if let Some(token) = jar
+        .get(REFRESH_TOKEN_COOKIE_NAME)
+        .and_then(|cookie| RefreshToken::parse(cookie.value()).ok())
+    {
+        let revoked = state
+            .refresh_token_store
+            .write()
+            .await
+            .revoke_family(&token)
+            .await;
+        if let Err(e) = revoked {
+            eprintln!("unable to revoke refresh token: {e}");
+        }
+    }
+
+    jar.remove(JWT_COOKIE_NAME)
+        .remove(REFRESH_TOKEN_COOKIE_NAME)
+}
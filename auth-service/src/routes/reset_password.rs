@@ -0,0 +1,107 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, CreateUserError, HashedPassword, PasswordResetToken,
+        PasswordResetTokenStoreError,
+    },
+    utils::constants::ARGON2_PARAMS,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResetPasswordResponse {
+    pub message: String,
+}
+
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token_error = |e| match e {
+        PasswordResetTokenStoreError::UnexpectedError => AuthAPIError::UnexpectedError,
+        _ => AuthAPIError::InvalidToken,
+    };
+
+    let token = PasswordResetToken::parse(&request.token).map_err(token_error)?;
+    let email = state
+        .password_reset_token_store
+        .read()
+        .await
+        .get_email(&token)
+        .await
+        .map_err(token_error)?;
+
+    // a rejected password doesn't burn the token, the user can pick another one
+    let password = state
+        .password_policy
+        .parse(&request.new_password, &email.password_context())
+        .map_err(|e| match e {
+            CreateUserError::WeakPassword(feedback) => AuthAPIError::WeakPassword(feedback),
+            CreateUserError::BreachedPassword => AuthAPIError::BreachedPassword,
+            _ => AuthAPIError::InvalidUserCredentials,
+        })?;
+    let hash = HashedPassword::hash(&password, ARGON2_PARAMS.clone())
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let owner = state
+        .password_reset_token_store
+        .write()
+        .await
+        .consume_token(&token)
+        .await
+        .map_err(token_error)?;
+    if owner != email {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    state
+        .user_store
+        .write()
+        .await
+        .update_password(email.as_ref(), hash)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    // log out everywhere, including logins waiting for their 2FA code: every token issued up to
+    // this second is revoked, logins from now on are dated after it
+    let issued_before =
+        usize::try_from(Utc::now().timestamp() + 1).map_err(|_| AuthAPIError::UnexpectedError)?;
+    state
+        .banned_tokens
+        .write()
+        .await
+        .revoke_issued_before(email.as_ref().to_owned(), issued_before)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_all(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(&email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let response = Json(ResetPasswordResponse {
+        message: "Password has been reset.".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
@@ -0,0 +1,82 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Form,
+};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{OAuthError, RefreshToken, RefreshTokenStoreError},
+    utils::auth::validate_token,
+};
+
+use super::token::authenticate_client;
+
+#[derive(Deserialize, Debug)]
+pub struct RevocationRequest {
+    pub token: Option<String>,
+    // access and refresh tokens are told apart by their format, the hint changes nothing
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+// RFC 7009: revokes an access token (JWT) or a refresh token.
+// Unknown, expired or already revoked tokens are not an error, there is nothing left to revoke.
+pub async fn revoke(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<RevocationRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client = authenticate_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    let token = request
+        .token
+        .ok_or(OAuthError::InvalidRequest("token is required"))?;
+
+    // A client only revokes what was issued to it. Login sessions have no client and are only
+    // ended by their user, through `/logout`.
+    if let Ok(refresh_token) = RefreshToken::parse(&token) {
+        let mut store = state.refresh_token_store.write().await;
+        match store.get_client(&refresh_token).await {
+            // the whole family, its access tokens expire on their own
+            Ok(Some(issued)) if issued.client_id == client.client_id => store
+                .revoke_family(&refresh_token)
+                .await
+                .map_err(|_| OAuthError::ServerError)?,
+            Ok(_) => return Err(OAuthError::UnauthorizedClient),
+            Err(RefreshTokenStoreError::UnexpectedError) => return Err(OAuthError::ServerError),
+            Err(_) => {}
+        }
+    } else {
+        let claims = {
+            let banned = state.banned_tokens.read().await;
+            validate_token(&token, &*state.keyring.read().await, &*banned)
+                .await
+                .ok()
+        };
+
+        if let Some(claims) = claims {
+            if claims.client_id.as_ref() != Some(&client.client_id) {
+                return Err(OAuthError::UnauthorizedClient);
+            }
+
+            state
+                .banned_tokens
+                .write()
+                .await
+                .add(claims.jti, claims.exp)
+                .await
+                .map_err(|_| OAuthError::ServerError)?;
+        }
+    }
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], StatusCode::OK))
+}
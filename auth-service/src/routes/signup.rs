@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, Password, User, UserStore, UserStoreError},
+    domain::{AuthAPIError, CreateUserError, Email, HashedPassword, User, UserStoreError},
+    utils::constants::ARGON2_PARAMS,
 };
 
 #[axum::debug_handler]
@@ -13,12 +14,21 @@ pub async fn signup(
 ) -> Result<impl IntoResponse, AuthAPIError> {
     let email = Email::parse(&_request.email).map_err(|_| AuthAPIError::InvalidUserCredentials)?;
 
-    let password =
-        Password::parse(&_request.password).map_err(|_| AuthAPIError::InvalidUserCredentials)?;
-
-    let user = User::new(email.as_ref(), password.as_ref(), _request.requires_2fa)
+    let password = state
+        .password_policy
+        .parse(&_request.password, &email.password_context())
+        .map_err(|e| match e {
+            CreateUserError::WeakPassword(feedback) => AuthAPIError::WeakPassword(feedback),
+            CreateUserError::BreachedPassword => AuthAPIError::BreachedPassword,
+            _ => AuthAPIError::InvalidUserCredentials,
+        })?;
+
+    let password = HashedPassword::hash(&password, ARGON2_PARAMS.clone())
+        .await
         .map_err(|_| AuthAPIError::UnexpectedError)?;
 
+    let user = User::new(email, password, _request.requires_2fa);
+
     let mut user_store = state.user_store.write().await;
 
     user_store.add_user(user).await.map_err(|e| match e {
@@ -0,0 +1,365 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::IntoResponse,
+    Form, Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        Authentication, AuthorizationCode, AuthorizationCodeStoreError, ClientStoreError,
+        DeviceCode, DeviceCodeStoreError, Email, OAuthClient, OAuthError, RefreshToken,
+        RefreshTokenClient, RefreshTokenStoreError,
+    },
+    utils::{
+        auth::{generate_access_token, TOKEN_TTL_SECONDS},
+        constants::DEVICE_CODE_GRANT_TYPE,
+        oidc::generate_id_token,
+    },
+};
+
+#[derive(Deserialize, Debug)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub client_id: Option<String>,
+    // client_secret_post, confidential clients may use Basic auth instead
+    pub client_secret: Option<String>,
+    pub code_verifier: Option<String>,
+    pub scope: Option<String>,
+    pub device_code: Option<String>,
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // with the `openid` scope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+    // for tokens issued on behalf of a user, rotated like the refresh token cookie
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+impl TokenResponse {
+    fn bearer(access_token: String, scope: Option<String>, id_token: Option<String>) -> Self {
+        Self {
+            access_token,
+            token_type: "Bearer".to_owned(),
+            expires_in: TOKEN_TTL_SECONDS,
+            scope,
+            id_token,
+            refresh_token: None,
+        }
+    }
+
+    fn with_refresh_token(self, refresh_token: &RefreshToken) -> Self {
+        Self {
+            refresh_token: Some(refresh_token.as_ref().to_owned()),
+            ..self
+        }
+    }
+}
+
+// OAuth token endpoint, trades an authorization code, a device code, a refresh token
+// or client credentials for an access token
+pub async fn token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<TokenRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let response = match request.grant_type.as_deref() {
+        Some("authorization_code") => authorization_code(&state, &headers, &request).await?,
+        Some("client_credentials") => client_credentials(&state, &headers, &request).await?,
+        Some(DEVICE_CODE_GRANT_TYPE) => device_code(&state, &headers, &request).await?,
+        Some("refresh_token") => refresh_token(&state, &headers, &request).await?,
+        Some(_) => return Err(OAuthError::UnsupportedGrantType),
+        None => return Err(OAuthError::InvalidRequest("grant_type is required")),
+    };
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(response)))
+}
+
+async fn authorization_code(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: &TokenRequest,
+) -> Result<TokenResponse, OAuthError> {
+    let (Some(code), Some(redirect_uri), Some(code_verifier)) =
+        (&request.code, &request.redirect_uri, &request.code_verifier)
+    else {
+        return Err(OAuthError::InvalidRequest(
+            "code, client_id, redirect_uri and code_verifier are required",
+        ));
+    };
+    let client = authenticate_client(
+        state,
+        headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+
+    let code = AuthorizationCode::parse(code).map_err(|_| OAuthError::InvalidGrant)?;
+    // the code is spent even when the checks below fail, it can't be guessed at twice
+    let grant = state
+        .authorization_code_store
+        .write()
+        .await
+        .take_code(&code)
+        .await
+        .map_err(|e| match e {
+            AuthorizationCodeStoreError::UnexpectedError => OAuthError::ServerError,
+            _ => OAuthError::InvalidGrant,
+        })?;
+
+    if grant.client_id != client.client_id
+        || grant.redirect_uri != *redirect_uri
+        || !grant.code_challenge.verify(code_verifier)
+    {
+        return Err(OAuthError::InvalidGrant);
+    }
+
+    let keyring = state.keyring.read().await;
+    let access_token = generate_access_token(
+        grant.email.as_ref(),
+        &grant.client_id,
+        grant.scope.as_deref(),
+        &keyring,
+    )
+    .map_err(|_| OAuthError::ServerError)?;
+    let id_token = if grant.has_scope("openid") {
+        Some(generate_id_token(&grant, &keyring).map_err(|_| OAuthError::ServerError)?)
+    } else {
+        None
+    };
+    drop(keyring);
+
+    let refresh_token = issue_refresh_token(
+        state,
+        grant.email,
+        grant.authentication,
+        RefreshTokenClient {
+            client_id: grant.client_id,
+            scope: grant.scope.clone(),
+        },
+    )
+    .await?;
+
+    Ok(TokenResponse::bearer(access_token, grant.scope, id_token)
+        .with_refresh_token(&refresh_token))
+}
+
+// A token of the client's own, for calls between backend services
+async fn client_credentials(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: &TokenRequest,
+) -> Result<TokenResponse, OAuthError> {
+    let client = authenticate_client(
+        state,
+        headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    if !client.is_confidential() {
+        return Err(OAuthError::UnauthorizedClient);
+    }
+    let scope = client.grant_scope(request.scope.as_deref())?;
+
+    let access_token = generate_access_token(
+        &client.client_id,
+        &client.client_id,
+        Some(&scope),
+        &*state.keyring.read().await,
+    )
+    .map_err(|_| OAuthError::ServerError)?;
+
+    Ok(TokenResponse::bearer(access_token, Some(scope), None))
+}
+
+// Once the user approved on the verification page, the device gets a token on their behalf
+async fn device_code(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: &TokenRequest,
+) -> Result<TokenResponse, OAuthError> {
+    let Some(device_code) = &request.device_code else {
+        return Err(OAuthError::InvalidRequest("device_code is required"));
+    };
+    let client = authenticate_client(
+        state,
+        headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+
+    let device_code = DeviceCode::parse(device_code).map_err(|_| OAuthError::InvalidGrant)?;
+    let grant = state
+        .device_code_store
+        .write()
+        .await
+        .poll(&device_code, &client.client_id)
+        .await
+        .map_err(|e| match e {
+            DeviceCodeStoreError::AuthorizationPending => OAuthError::AuthorizationPending,
+            DeviceCodeStoreError::SlowDown => OAuthError::SlowDown,
+            DeviceCodeStoreError::AccessDenied => OAuthError::AccessDenied,
+            DeviceCodeStoreError::Expired => OAuthError::ExpiredToken,
+            DeviceCodeStoreError::UnexpectedError => OAuthError::ServerError,
+            _ => OAuthError::InvalidGrant,
+        })?;
+
+    let access_token = generate_access_token(
+        grant.email.as_ref(),
+        &client.client_id,
+        grant.request.scope.as_deref(),
+        &*state.keyring.read().await,
+    )
+    .map_err(|_| OAuthError::ServerError)?;
+    let refresh_token = issue_refresh_token(
+        state,
+        grant.email,
+        grant.authentication,
+        RefreshTokenClient {
+            client_id: client.client_id,
+            scope: grant.request.scope.clone(),
+        },
+    )
+    .await?;
+
+    Ok(
+        TokenResponse::bearer(access_token, grant.request.scope, None)
+            .with_refresh_token(&refresh_token),
+    )
+}
+
+// A new access token for the user the refresh token was issued for, with the scope they granted.
+// The refresh token is rotated, replaying an old one revokes the family like at `/refresh`.
+async fn refresh_token(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: &TokenRequest,
+) -> Result<TokenResponse, OAuthError> {
+    let Some(token) = &request.refresh_token else {
+        return Err(OAuthError::InvalidRequest("refresh_token is required"));
+    };
+    let client = authenticate_client(
+        state,
+        headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+
+    let token = RefreshToken::parse(token).map_err(|_| OAuthError::InvalidGrant)?;
+    let new_token = RefreshToken::default();
+    let mut store = state.refresh_token_store.write().await;
+    let (email, _) = store
+        .rotate_token(&token, Some(&client.client_id), &new_token)
+        .await
+        .map_err(|e| match e {
+            RefreshTokenStoreError::UnexpectedError => OAuthError::ServerError,
+            _ => OAuthError::InvalidGrant,
+        })?;
+    let scope = store
+        .get_client(&new_token)
+        .await
+        .map_err(|_| OAuthError::ServerError)?
+        .and_then(|issued| issued.scope);
+    drop(store);
+
+    let access_token = generate_access_token(
+        email.as_ref(),
+        &client.client_id,
+        scope.as_deref(),
+        &*state.keyring.read().await,
+    )
+    .map_err(|_| OAuthError::ServerError)?;
+
+    Ok(TokenResponse::bearer(access_token, scope, None).with_refresh_token(&new_token))
+}
+
+// Starts a token family for the client, like a login does for the browser
+async fn issue_refresh_token(
+    state: &AppState,
+    email: Email,
+    authentication: Authentication,
+    client: RefreshTokenClient,
+) -> Result<RefreshToken, OAuthError> {
+    let refresh_token = RefreshToken::default();
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .add_token(email, authentication, Some(client), &refresh_token)
+        .await
+        .map_err(|_| OAuthError::ServerError)?;
+    Ok(refresh_token)
+}
+
+// client_secret_basic, else client_secret_post, else a public client sending only its client_id
+pub(crate) async fn authenticate_client(
+    state: &AppState,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<OAuthClient, OAuthError> {
+    let (client_id, client_secret) = match basic_credentials(headers)? {
+        Some((client_id, client_secret)) => (client_id, Some(client_secret)),
+        None => (
+            client_id
+                .ok_or(OAuthError::InvalidRequest(
+                    "client_id or HTTP Basic client authentication is required",
+                ))?
+                .to_owned(),
+            client_secret.map(str::to_owned),
+        ),
+    };
+
+    let client = state
+        .client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+        .map_err(|e| match e {
+            ClientStoreError::ClientNotFound => OAuthError::InvalidClient,
+            _ => OAuthError::ServerError,
+        })?;
+
+    client.authenticate(client_secret.as_deref()).await?;
+    Ok(client)
+}
+
+// `Authorization: Basic base64(client_id:client_secret)`
+fn basic_credentials(headers: &HeaderMap) -> Result<Option<(String, String)>, OAuthError> {
+    let Some(value) = headers.get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+
+    let credentials = value
+        .to_str()
+        .ok()
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("basic"))
+        .and_then(|(_, encoded)| STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or(OAuthError::InvalidClient)?;
+    let (client_id, client_secret) = credentials
+        .split_once(':')
+        .ok_or(OAuthError::InvalidClient)?;
+
+    Ok(Some((client_id.to_owned(), client_secret.to_owned())))
+}
@@ -1,4 +1,45 @@
-use axum::{http::StatusCode, response::IntoResponse};
-pub async fn verify_2fa() -> impl IntoResponse {
-    StatusCode::OK.into_response()
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Authentication, Email, LoginAttemptId, TwoFACode, TwoFACodeStoreError},
+    routes::start_session,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct Verify2FARequest {
+    pub email: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: String,
+}
+
+pub async fn verify_2fa(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<Verify2FARequest>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let email = Email::parse(&request.email).map_err(|_| AuthAPIError::InvalidUserCredentials)?;
+    let login_attempt_id = LoginAttemptId::parse(&request.login_attempt_id)
+        .map_err(|_| AuthAPIError::InvalidUserCredentials)?;
+    let two_fa_code =
+        TwoFACode::parse(&request.two_fa_code).map_err(|_| AuthAPIError::InvalidUserCredentials)?;
+
+    state
+        .two_fa_code_store
+        .write()
+        .await
+        .verify_code(&email, &login_attempt_id, &two_fa_code)
+        .await
+        .map_err(|e| match e {
+            TwoFACodeStoreError::UnexpectedError => AuthAPIError::UnexpectedError,
+            _ => AuthAPIError::Unauthorized,
+        })?;
+
+    let jar = start_session(&state, &email, Authentication::now(true), jar).await?;
+
+    Ok((jar, StatusCode::OK))
 }
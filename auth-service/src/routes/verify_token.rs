@@ -1,8 +1,14 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
 
 use crate::{
-    app_state::AppState, domain::AuthAPIError, services::HashsetBannedTokenStore,
-    utils::auth::validate_token,
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::auth::{bearer_token, validate_token},
 };
 
 #[derive(serde::Deserialize, Debug)]
@@ -10,19 +16,31 @@ pub struct VerifyTokenRequest {
     token: String,
 }
 
+// API clients send `Authorization: Bearer <jwt>`, app-service keeps sending the token in the body
 pub async fn verify_token(
     State(_state): State<AppState>,
-    Json(_request): Json<VerifyTokenRequest>,
-) -> Result<impl IntoResponse, AuthAPIError> {
-    let token = _request.token;
-
-    let banned: HashsetBannedTokenStore = _state.banned_tokens.read().await.clone();
+    headers: HeaderMap,
+    _request: Result<Json<VerifyTokenRequest>, JsonRejection>,
+) -> Result<Response, AuthAPIError> {
+    let token = match bearer_token(&headers)? {
+        Some(token) => token.to_owned(),
+        None => match _request {
+            Ok(Json(request)) => request.token,
+            Err(rejection) => return Ok(rejection.into_response()),
+        },
+    };
 
     if token.is_empty() {
         return Err(AuthAPIError::MalformedToken);
     }
 
-    if validate_token(&token, banned).await.is_err() {
+    let banned = _state.banned_tokens.read().await;
+
+    // login sessions only, OAuth access tokens are checked at `/introspect`
+    let is_session = validate_token(&token, &*_state.keyring.read().await, &*banned)
+        .await
+        .is_ok_and(|claims| claims.is_session());
+    if !is_session {
         return Err(AuthAPIError::InvalidToken);
     }
 
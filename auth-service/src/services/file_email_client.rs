@@ -0,0 +1,83 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::{
+    domain::{Email, EmailClient, EmailClientError, EmailMessage},
+    utils::email::to_mime_message,
+};
+use chrono::Utc;
+use std::path::PathBuf;
+
+// Writes every message as an .eml file into `outbox`, handy for local development
+#[derive(Debug, Clone)]
+pub struct FileEmailClient {
+    outbox: PathBuf,
+    sender: String,
+}
+
+impl FileEmailClient {
+    pub fn new(outbox: impl Into<PathBuf>, sender: &str) -> Self {
+        Self {
+            outbox: outbox.into(),
+            sender: sender.to_owned(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for FileEmailClient {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<(), EmailClientError> {
+        let message = to_mime_message(&self.sender, recipient, message)?;
+
+        tokio::fs::create_dir_all(&self.outbox)
+            .await
+            .map_err(|e| EmailClientError::DeliveryFailed(e.to_string()))?;
+
+        let path = self.outbox.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            uuid::Uuid::new_v4()
+        ));
+
+        tokio::fs::write(path, message.formatted())
+            .await
+            .map_err(|e| EmailClientError::DeliveryFailed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_email_writes_eml_file() {
+        let outbox = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let client = FileEmailClient::new(&outbox, "no-reply@example.com");
+        let recipient = Email::parse("hnariman@gmail.com").unwrap();
+        let message = EmailMessage {
+            subject: "Your code".to_owned(),
+            text: "123456".to_owned(),
+            html: "<b>123456</b>".to_owned(),
+        };
+
+        client.send_email(&recipient, &message).await.unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(&outbox)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+
+        let eml = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(eml.contains("From: no-reply@example.com\r\n"));
+        assert!(eml.contains("To: hnariman@gmail.com\r\n"));
+        assert!(eml.contains("Subject: Your code\r\n"));
+        assert!(eml.contains("<b>123456</b>"));
+
+        std::fs::remove_dir_all(outbox).unwrap();
+    }
+}
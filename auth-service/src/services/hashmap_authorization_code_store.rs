@@ -0,0 +1,141 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::{
+    domain::{
+        AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError, AuthorizationGrant,
+    },
+    utils::constants::AUTHORIZATION_CODE_TTL_SECONDS,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone)]
+pub struct PendingGrant {
+    pub grant: AuthorizationGrant,
+    pub created_at: DateTime<Utc>,
+}
+
+// Keyed by the code hash, a leaked store can't be used to get tokens
+#[derive(Debug, Clone)]
+pub struct HashmapAuthorizationCodeStore {
+    pub codes: Arc<Mutex<HashMap<String, PendingGrant>>>,
+    ttl: Duration,
+}
+
+impl HashmapAuthorizationCodeStore {
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            codes: Arc::default(),
+            ttl,
+        }
+    }
+
+    fn is_expired(&self, pending: &PendingGrant) -> bool {
+        Utc::now() >= pending.created_at + self.ttl
+    }
+}
+
+impl Default for HashmapAuthorizationCodeStore {
+    fn default() -> Self {
+        Self::new(Duration::seconds(AUTHORIZATION_CODE_TTL_SECONDS))
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for HashmapAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: &AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let mut codes = self
+            .codes
+            .lock()
+            .map_err(|_| AuthorizationCodeStoreError::UnexpectedError)?;
+
+        codes.retain(|_, pending| !self.is_expired(pending));
+        codes.insert(
+            code.hash(),
+            PendingGrant {
+                grant,
+                created_at: Utc::now(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn take_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        let pending = self
+            .codes
+            .lock()
+            .map_err(|_| AuthorizationCodeStoreError::UnexpectedError)?
+            .remove(&code.hash())
+            .ok_or(AuthorizationCodeStoreError::CodeNotFound)?;
+
+        if self.is_expired(&pending) {
+            return Err(AuthorizationCodeStoreError::Expired);
+        }
+        Ok(pending.grant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{Authentication, CodeChallenge, Email};
+
+    use super::*;
+
+    fn grant() -> AuthorizationGrant {
+        AuthorizationGrant {
+            client_id: "spa".to_owned(),
+            redirect_uri: "http://localhost:8000/callback".to_owned(),
+            email: Email::parse("test@example.com").unwrap(),
+            code_challenge: CodeChallenge::parse(
+                "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM",
+                Some("S256"),
+            )
+            .unwrap(),
+            scope: None,
+            nonce: None,
+            authentication: Authentication {
+                auth_time: 1_700_000_000,
+                amr: vec!["pwd".to_owned()],
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_code_is_single_use() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+
+        store.add_code(&code, grant()).await.unwrap();
+
+        assert!(!store.codes.lock().unwrap().contains_key(code.as_ref()));
+        assert_eq!(store.take_code(&code).await, Ok(grant()));
+        assert_eq!(
+            store.take_code(&code).await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_code() {
+        let mut store = HashmapAuthorizationCodeStore::new(Duration::zero());
+        let code = AuthorizationCode::default();
+
+        store.add_code(&code, grant()).await.unwrap();
+
+        assert_eq!(
+            store.take_code(&code).await,
+            Err(AuthorizationCodeStoreError::Expired)
+        );
+    }
+}
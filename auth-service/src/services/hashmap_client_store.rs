@@ -0,0 +1,108 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::domain::{ClientStore, ClientStoreError, OAuthClient};
+use serde::Deserialize;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Deserialize)]
+struct ClientsFile {
+    clients: Vec<OAuthClient>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct HashmapClientStore {
+    pub clients: Arc<Mutex<HashMap<String, OAuthClient>>>,
+}
+
+impl HashmapClientStore {
+    /// Clients registered in a JSON file: `{"clients": [{"client_id", "name", "redirect_uris"}]}`
+    ///
+    /// # Errors
+    /// When the file can't be read or parsed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let file: ClientsFile = serde_json::from_slice(&std::fs::read(path)?)?;
+
+        let clients = file
+            .clients
+            .into_iter()
+            .map(|client| (client.client_id.clone(), client))
+            .collect();
+        Ok(Self {
+            clients: Arc::new(Mutex::new(clients)),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientStore for HashmapClientStore {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), ClientStoreError> {
+        match self
+            .clients
+            .lock()
+            .map_err(|_| ClientStoreError::UnexpectedError)?
+            .entry(client.client_id.clone())
+        {
+            Entry::Occupied(_) => Err(ClientStoreError::ClientAlreadyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(client);
+                Ok(())
+            }
+        }
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, ClientStoreError> {
+        self.clients
+            .lock()
+            .map_err(|_| ClientStoreError::UnexpectedError)?
+            .get(client_id)
+            .cloned()
+            .ok_or(ClientStoreError::ClientNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> OAuthClient {
+        OAuthClient {
+            client_id: "spa".to_owned(),
+            name: "SPA".to_owned(),
+            redirect_uris: vec!["http://localhost:8000/callback".to_owned()],
+            ..OAuthClient::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_client() {
+        let mut store = HashmapClientStore::default();
+
+        store.add_client(client()).await.unwrap();
+
+        assert_eq!(store.get_client("spa").await, Ok(client()));
+        assert_eq!(
+            store.add_client(client()).await,
+            Err(ClientStoreError::ClientAlreadyExists)
+        );
+        assert_eq!(
+            store.get_client("other").await,
+            Err(ClientStoreError::ClientNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load() {
+        let store = HashmapClientStore::load("tests/fixtures/oauth_clients.json").unwrap();
+
+        let client = store.get_client("test-client").await.unwrap();
+        assert!(client.allows_redirect("http://localhost:8000/callback"));
+        assert!(!client.is_confidential());
+        let service = store.get_client("test-service").await.unwrap();
+        assert!(service.is_confidential());
+        assert_eq!(service.scopes, vec!["introspect", "read"]);
+    }
+}
@@ -0,0 +1,350 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::{
+    domain::{
+        Authentication, DeviceCode, DeviceCodeStore, DeviceCodeStoreError, DeviceGrant,
+        DeviceRequest, Email, UserCode,
+    },
+    utils::constants::{DEVICE_CODE_POLL_INTERVAL_SECONDS, DEVICE_CODE_TTL_SECONDS},
+};
+use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceAnswer {
+    Pending,
+    Approved(Email, Authentication),
+    Denied,
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingDevice {
+    pub request: DeviceRequest,
+    pub user_code: UserCode,
+    pub answer: DeviceAnswer,
+    pub created_at: DateTime<Utc>,
+    pub polled_at: Option<DateTime<Utc>>,
+    // grows every time the device polls too early
+    pub interval: Duration,
+}
+
+// Keyed by the device code hash, a leaked store can't be used to get tokens
+#[derive(Debug, Clone)]
+pub struct HashmapDeviceCodeStore {
+    pub requests: Arc<Mutex<HashMap<String, PendingDevice>>>,
+    ttl: Duration,
+    interval: Duration,
+}
+
+impl HashmapDeviceCodeStore {
+    #[must_use]
+    pub fn new(ttl: Duration, interval: Duration) -> Self {
+        Self {
+            requests: Arc::default(),
+            ttl,
+            interval,
+        }
+    }
+
+    fn is_expired(&self, pending: &PendingDevice) -> bool {
+        Utc::now() >= pending.created_at + self.ttl
+    }
+
+    // Only requests still waiting for the user can be looked up by their user code
+    fn with_unanswered<T>(
+        &self,
+        user_code: &UserCode,
+        f: impl FnOnce(&mut PendingDevice) -> T,
+    ) -> Result<T, DeviceCodeStoreError> {
+        let mut requests = self
+            .requests
+            .lock()
+            .map_err(|_| DeviceCodeStoreError::UnexpectedError)?;
+
+        let pending = requests
+            .values_mut()
+            .find(|pending| {
+                pending.user_code == *user_code && pending.answer == DeviceAnswer::Pending
+            })
+            .ok_or(DeviceCodeStoreError::CodeNotFound)?;
+
+        if self.is_expired(pending) {
+            return Err(DeviceCodeStoreError::Expired);
+        }
+        Ok(f(pending))
+    }
+}
+
+impl Default for HashmapDeviceCodeStore {
+    fn default() -> Self {
+        Self::new(
+            Duration::seconds(DEVICE_CODE_TTL_SECONDS),
+            Duration::seconds(DEVICE_CODE_POLL_INTERVAL_SECONDS),
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl DeviceCodeStore for HashmapDeviceCodeStore {
+    async fn add_request(
+        &mut self,
+        device_code: &DeviceCode,
+        user_code: &UserCode,
+        request: DeviceRequest,
+    ) -> Result<(), DeviceCodeStoreError> {
+        let mut requests = self
+            .requests
+            .lock()
+            .map_err(|_| DeviceCodeStoreError::UnexpectedError)?;
+
+        requests.retain(|_, pending| !self.is_expired(pending));
+        if requests
+            .values()
+            .any(|pending| pending.user_code == *user_code)
+        {
+            return Err(DeviceCodeStoreError::UserCodeInUse);
+        }
+        requests.insert(
+            device_code.hash(),
+            PendingDevice {
+                request,
+                user_code: user_code.clone(),
+                answer: DeviceAnswer::Pending,
+                created_at: Utc::now(),
+                polled_at: None,
+                interval: self.interval,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_request(
+        &self,
+        user_code: &UserCode,
+    ) -> Result<DeviceRequest, DeviceCodeStoreError> {
+        self.with_unanswered(user_code, |pending| pending.request.clone())
+    }
+
+    async fn approve(
+        &mut self,
+        user_code: &UserCode,
+        email: Email,
+        authentication: Authentication,
+    ) -> Result<(), DeviceCodeStoreError> {
+        self.with_unanswered(user_code, |pending| {
+            pending.answer = DeviceAnswer::Approved(email, authentication);
+        })
+    }
+
+    async fn deny(&mut self, user_code: &UserCode) -> Result<(), DeviceCodeStoreError> {
+        self.with_unanswered(user_code, |pending| pending.answer = DeviceAnswer::Denied)
+    }
+
+    async fn poll(
+        &mut self,
+        device_code: &DeviceCode,
+        client_id: &str,
+    ) -> Result<DeviceGrant, DeviceCodeStoreError> {
+        let mut requests = self
+            .requests
+            .lock()
+            .map_err(|_| DeviceCodeStoreError::UnexpectedError)?;
+        let hash = device_code.hash();
+        let pending = requests
+            .get_mut(&hash)
+            .ok_or(DeviceCodeStoreError::CodeNotFound)?;
+
+        if pending.request.client_id != client_id {
+            return Err(DeviceCodeStoreError::ClientMismatch);
+        }
+        if self.is_expired(pending) {
+            requests.remove(&hash);
+            return Err(DeviceCodeStoreError::Expired);
+        }
+
+        let now = Utc::now();
+        let too_early = pending
+            .polled_at
+            .is_some_and(|polled_at| now < polled_at + pending.interval);
+        pending.polled_at = Some(now);
+        if too_early {
+            // RFC 8628 section 3.5: the device has to wait 5 more seconds from then on
+            pending.interval += Duration::seconds(5);
+            return Err(DeviceCodeStoreError::SlowDown);
+        }
+
+        match &pending.answer {
+            DeviceAnswer::Pending => Err(DeviceCodeStoreError::AuthorizationPending),
+            DeviceAnswer::Denied => {
+                requests.remove(&hash);
+                Err(DeviceCodeStoreError::AccessDenied)
+            }
+            DeviceAnswer::Approved(email, authentication) => {
+                let grant = DeviceGrant {
+                    request: pending.request.clone(),
+                    email: email.clone(),
+                    authentication: authentication.clone(),
+                };
+                requests.remove(&hash);
+                Ok(grant)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> DeviceRequest {
+        DeviceRequest {
+            client_id: "cli".to_owned(),
+            scope: Some("read".to_owned()),
+        }
+    }
+
+    fn authentication() -> Authentication {
+        Authentication {
+            auth_time: 1_700_000_000,
+            amr: vec!["pwd".to_owned()],
+        }
+    }
+
+    // Polling as often as the device likes
+    fn store() -> HashmapDeviceCodeStore {
+        HashmapDeviceCodeStore::new(Duration::seconds(60), Duration::zero())
+    }
+
+    #[tokio::test]
+    async fn test_grant_is_redeemed_once_approved() {
+        let mut store = store();
+        let (device_code, user_code) = (DeviceCode::default(), UserCode::default());
+        let email = Email::parse("test@example.com").unwrap();
+
+        store
+            .add_request(&device_code, &user_code, request())
+            .await
+            .unwrap();
+        assert!(!store
+            .requests
+            .lock()
+            .unwrap()
+            .contains_key(device_code.as_ref()));
+        assert_eq!(store.get_request(&user_code).await, Ok(request()));
+        assert_eq!(
+            store.poll(&device_code, "cli").await,
+            Err(DeviceCodeStoreError::AuthorizationPending)
+        );
+
+        store
+            .approve(&user_code, email.clone(), authentication())
+            .await
+            .unwrap();
+        // answered once only
+        assert_eq!(
+            store.deny(&user_code).await,
+            Err(DeviceCodeStoreError::CodeNotFound)
+        );
+        // another client can't take the grant away
+        assert_eq!(
+            store.poll(&device_code, "other-client").await,
+            Err(DeviceCodeStoreError::ClientMismatch)
+        );
+        assert_eq!(
+            store.poll(&device_code, "cli").await,
+            Ok(DeviceGrant {
+                request: request(),
+                email,
+                authentication: authentication(),
+            })
+        );
+        assert_eq!(
+            store.poll(&device_code, "cli").await,
+            Err(DeviceCodeStoreError::CodeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_denied_request() {
+        let mut store = store();
+        let (device_code, user_code) = (DeviceCode::default(), UserCode::default());
+
+        store
+            .add_request(&device_code, &user_code, request())
+            .await
+            .unwrap();
+        store.deny(&user_code).await.unwrap();
+
+        assert_eq!(
+            store.poll(&device_code, "cli").await,
+            Err(DeviceCodeStoreError::AccessDenied)
+        );
+        assert_eq!(
+            store.poll(&device_code, "cli").await,
+            Err(DeviceCodeStoreError::CodeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_polling_too_often_slows_down() {
+        let mut store = HashmapDeviceCodeStore::new(Duration::seconds(60), Duration::seconds(5));
+        let (device_code, user_code) = (DeviceCode::default(), UserCode::default());
+
+        store
+            .add_request(&device_code, &user_code, request())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.poll(&device_code, "cli").await,
+            Err(DeviceCodeStoreError::AuthorizationPending)
+        );
+        assert_eq!(
+            store.poll(&device_code, "cli").await,
+            Err(DeviceCodeStoreError::SlowDown)
+        );
+        let interval = store.requests.lock().unwrap()[&device_code.hash()].interval;
+        assert_eq!(interval, Duration::seconds(10));
+    }
+
+    #[tokio::test]
+    async fn test_expired_request() {
+        let mut store = HashmapDeviceCodeStore::new(Duration::zero(), Duration::zero());
+        let (device_code, user_code) = (DeviceCode::default(), UserCode::default());
+
+        store
+            .add_request(&device_code, &user_code, request())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_request(&user_code).await,
+            Err(DeviceCodeStoreError::Expired)
+        );
+        assert_eq!(
+            store.poll(&device_code, "cli").await,
+            Err(DeviceCodeStoreError::Expired)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_user_code_is_unique() {
+        let mut store = store();
+        let user_code = UserCode::default();
+
+        store
+            .add_request(&DeviceCode::default(), &user_code, request())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store
+                .add_request(&DeviceCode::default(), &user_code, request())
+                .await,
+            Err(DeviceCodeStoreError::UserCodeInUse)
+        );
+    }
+}
@@ -0,0 +1,171 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::{
+    domain::{Email, PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError},
+    utils::constants::PASSWORD_RESET_TOKEN_TTL_SECONDS,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone)]
+pub struct PendingReset {
+    pub email: Email,
+    pub created_at: DateTime<Utc>,
+}
+
+// Keyed by the token hash, a leaked store can't be used to reset passwords
+#[derive(Debug, Clone)]
+pub struct HashmapPasswordResetTokenStore {
+    pub tokens: Arc<Mutex<HashMap<String, PendingReset>>>,
+    ttl: Duration,
+}
+
+impl HashmapPasswordResetTokenStore {
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            tokens: Arc::default(),
+            ttl,
+        }
+    }
+
+    fn is_expired(&self, pending: &PendingReset) -> bool {
+        Utc::now() >= pending.created_at + self.ttl
+    }
+}
+
+impl Default for HashmapPasswordResetTokenStore {
+    fn default() -> Self {
+        Self::new(Duration::seconds(PASSWORD_RESET_TOKEN_TTL_SECONDS))
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashmapPasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: &PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let mut tokens = self
+            .tokens
+            .lock()
+            .map_err(|_| PasswordResetTokenStoreError::UnexpectedError)?;
+
+        // only the latest link works, expired ones are dropped on the way
+        tokens.retain(|_, pending| pending.email != email && !self.is_expired(pending));
+        tokens.insert(
+            token.hash(),
+            PendingReset {
+                email,
+                created_at: Utc::now(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_email(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        let tokens = self
+            .tokens
+            .lock()
+            .map_err(|_| PasswordResetTokenStoreError::UnexpectedError)?;
+
+        match tokens.get(&token.hash()) {
+            Some(pending) if self.is_expired(pending) => Err(PasswordResetTokenStoreError::Expired),
+            Some(pending) => Ok(pending.email.clone()),
+            None => Err(PasswordResetTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn consume_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        let pending = self
+            .tokens
+            .lock()
+            .map_err(|_| PasswordResetTokenStoreError::UnexpectedError)?
+            .remove(&token.hash())
+            .ok_or(PasswordResetTokenStoreError::TokenNotFound)?;
+
+        if self.is_expired(&pending) {
+            return Err(PasswordResetTokenStoreError::Expired);
+        }
+        Ok(pending.email)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse("test@example.com").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_consume_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let token = PasswordResetToken::default();
+
+        store.add_token(email(), &token).await.unwrap();
+
+        assert_eq!(store.get_email(&token).await, Ok(email()));
+        assert_eq!(store.consume_token(&token).await, Ok(email()));
+        assert_eq!(
+            store.consume_token(&token).await,
+            Err(PasswordResetTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_only_token_hash_is_stored() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let token = PasswordResetToken::default();
+
+        store.add_token(email(), &token).await.unwrap();
+
+        let tokens = store.tokens.lock().unwrap();
+        assert!(tokens.contains_key(&token.hash()));
+        assert!(!tokens.contains_key(token.as_ref()));
+    }
+
+    #[tokio::test]
+    async fn test_new_token_replaces_old_one() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let old_token = PasswordResetToken::default();
+        let new_token = PasswordResetToken::default();
+
+        store.add_token(email(), &old_token).await.unwrap();
+        store.add_token(email(), &new_token).await.unwrap();
+
+        assert_eq!(
+            store.get_email(&old_token).await,
+            Err(PasswordResetTokenStoreError::TokenNotFound)
+        );
+        assert_eq!(store.get_email(&new_token).await, Ok(email()));
+    }
+
+    #[tokio::test]
+    async fn test_expired_token() {
+        let mut store = HashmapPasswordResetTokenStore::new(Duration::zero());
+        let token = PasswordResetToken::default();
+
+        store.add_token(email(), &token).await.unwrap();
+
+        assert_eq!(
+            store.get_email(&token).await,
+            Err(PasswordResetTokenStoreError::Expired)
+        );
+        assert_eq!(
+            store.consume_token(&token).await,
+            Err(PasswordResetTokenStoreError::Expired)
+        );
+    }
+}
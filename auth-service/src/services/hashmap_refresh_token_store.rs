@@ -0,0 +1,484 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::{
+    domain::{
+        Authentication, Email, RefreshToken, RefreshTokenClient, RefreshTokenStore,
+        RefreshTokenStoreError,
+    },
+    utils::constants::{REFRESH_TOKEN_FAMILY_TTL_SECONDS, REFRESH_TOKEN_TTL_SECONDS},
+};
+use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone)]
+pub struct IssuedRefreshToken {
+    pub email: Email,
+    pub authentication: Authentication,
+    // OAuth client the family was issued to, none for a login session
+    pub client: Option<RefreshTokenClient>,
+    pub family: String,
+    // when the login happened, the family doesn't outlive it by more than the family TTL
+    pub family_created_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    // rotated tokens are kept until they expire so a replay can be detected
+    pub rotated_at: Option<DateTime<Utc>>,
+}
+
+// Keyed by the token hash, a leaked store can't be used to refresh sessions
+#[derive(Debug, Clone)]
+pub struct HashmapRefreshTokenStore {
+    pub tokens: Arc<Mutex<HashMap<String, IssuedRefreshToken>>>,
+    ttl: Duration,
+    family_ttl: Duration,
+}
+
+impl HashmapRefreshTokenStore {
+    #[must_use]
+    pub fn new(ttl: Duration, family_ttl: Duration) -> Self {
+        Self {
+            tokens: Arc::default(),
+            ttl,
+            family_ttl,
+        }
+    }
+
+    fn is_expired(&self, issued: &IssuedRefreshToken, now: DateTime<Utc>) -> bool {
+        now >= issued.created_at + self.ttl || now >= issued.family_created_at + self.family_ttl
+    }
+
+    /// Drops expired tokens, rotated ones included, returns how many were removed.
+    ///
+    /// # Errors
+    /// When the lock is poisoned.
+    pub fn prune_expired(&self, now: DateTime<Utc>) -> Result<usize, RefreshTokenStoreError> {
+        let mut tokens = self
+            .tokens
+            .lock()
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        let before = tokens.len();
+        tokens.retain(|_, issued| !self.is_expired(issued, now));
+        Ok(before - tokens.len())
+    }
+
+    /// Evicts expired tokens in the background every `period`, the task runs until aborted.
+    #[must_use]
+    pub fn spawn_pruning(&self, period: std::time::Duration) -> JoinHandle<()> {
+        let store = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                match store.prune_expired(Utc::now()) {
+                    Ok(0) => {}
+                    Ok(pruned) => eprintln!("refresh token store: pruned {pruned} tokens"),
+                    Err(e) => eprintln!("unable to prune refresh tokens: {e}"),
+                }
+            }
+        })
+    }
+}
+
+impl Default for HashmapRefreshTokenStore {
+    fn default() -> Self {
+        Self::new(
+            Duration::seconds(REFRESH_TOKEN_TTL_SECONDS),
+            Duration::seconds(REFRESH_TOKEN_FAMILY_TTL_SECONDS),
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        authentication: Authentication,
+        client: Option<RefreshTokenClient>,
+        token: &RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let mut tokens = self
+            .tokens
+            .lock()
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        let now = Utc::now();
+        tokens.insert(
+            token.hash(),
+            IssuedRefreshToken {
+                email,
+                authentication,
+                client,
+                family: uuid::Uuid::new_v4().to_string(),
+                family_created_at: now,
+                created_at: now,
+                rotated_at: None,
+            },
+        );
+        Ok(())
+    }
+
+    async fn rotate_token(
+        &mut self,
+        token: &RefreshToken,
+        client_id: Option<&str>,
+        new_token: &RefreshToken,
+    ) -> Result<(Email, Authentication), RefreshTokenStoreError> {
+        let mut tokens = self
+            .tokens
+            .lock()
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        let issued = tokens
+            .get_mut(&token.hash())
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        if issued
+            .client
+            .as_ref()
+            .map(|client| client.client_id.as_str())
+            != client_id
+        {
+            return Err(RefreshTokenStoreError::ClientMismatch);
+        }
+        if issued.rotated_at.is_some() {
+            let family = issued.family.clone();
+            tokens.retain(|_, issued| issued.family != family);
+            return Err(RefreshTokenStoreError::Reused);
+        }
+        let now = Utc::now();
+        if self.is_expired(issued, now) {
+            return Err(RefreshTokenStoreError::Expired);
+        }
+
+        issued.rotated_at = Some(now);
+        let next = IssuedRefreshToken {
+            email: issued.email.clone(),
+            authentication: issued.authentication.clone(),
+            client: issued.client.clone(),
+            family: issued.family.clone(),
+            family_created_at: issued.family_created_at,
+            created_at: now,
+            rotated_at: None,
+        };
+        let session = (next.email.clone(), next.authentication.clone());
+        tokens.insert(new_token.hash(), next);
+        Ok(session)
+    }
+
+    async fn get_client(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<Option<RefreshTokenClient>, RefreshTokenStoreError> {
+        let tokens = self
+            .tokens
+            .lock()
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        match tokens.get(&token.hash()) {
+            Some(issued) if self.is_expired(issued, Utc::now()) => {
+                Err(RefreshTokenStoreError::Expired)
+            }
+            Some(issued) => Ok(issued.client.clone()),
+            None => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn revoke_family(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError> {
+        let mut tokens = self
+            .tokens
+            .lock()
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        if let Some(family) = tokens
+            .get(&token.hash())
+            .map(|issued| issued.family.clone())
+        {
+            tokens.retain(|_, issued| issued.family != family);
+        }
+        Ok(())
+    }
+
+    async fn revoke_all(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        self.tokens
+            .lock()
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?
+            .retain(|_, issued| issued.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse("test@example.com").unwrap()
+    }
+
+    fn authentication() -> Authentication {
+        Authentication {
+            auth_time: 1_700_000_000,
+            amr: vec!["pwd".to_owned()],
+        }
+    }
+
+    fn session() -> (Email, Authentication) {
+        (email(), authentication())
+    }
+
+    #[tokio::test]
+    async fn test_rotate_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let next = RefreshToken::default();
+
+        store
+            .add_token(email(), authentication(), None, &token)
+            .await
+            .unwrap();
+
+        assert_eq!(store.rotate_token(&token, None, &next).await, Ok(session()));
+        assert_eq!(
+            store
+                .rotate_token(&next, None, &RefreshToken::default())
+                .await,
+            Ok(session())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_only_token_hash_is_stored() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+
+        store
+            .add_token(email(), authentication(), None, &token)
+            .await
+            .unwrap();
+
+        let tokens = store.tokens.lock().unwrap();
+        assert!(tokens.contains_key(&token.hash()));
+        assert!(!tokens.contains_key(token.as_ref()));
+    }
+
+    #[tokio::test]
+    async fn test_reuse_revokes_family() {
+        // any replay is caught, even right after the rotation
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let next = RefreshToken::default();
+        let other_session = RefreshToken::default();
+
+        store
+            .add_token(email(), authentication(), None, &token)
+            .await
+            .unwrap();
+        store
+            .add_token(email(), authentication(), None, &other_session)
+            .await
+            .unwrap();
+        store.rotate_token(&token, None, &next).await.unwrap();
+
+        assert_eq!(
+            store
+                .rotate_token(&token, None, &RefreshToken::default())
+                .await,
+            Err(RefreshTokenStoreError::Reused)
+        );
+        assert_eq!(
+            store
+                .rotate_token(&next, None, &RefreshToken::default())
+                .await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+        // other logins of the same user are left alone
+        assert_eq!(
+            store
+                .rotate_token(&other_session, None, &RefreshToken::default())
+                .await,
+            Ok(session())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family_and_all() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let first = RefreshToken::default();
+        let second = RefreshToken::default();
+        let third = RefreshToken::default();
+
+        store
+            .add_token(email(), authentication(), None, &first)
+            .await
+            .unwrap();
+        store
+            .add_token(email(), authentication(), None, &second)
+            .await
+            .unwrap();
+        store
+            .add_token(email(), authentication(), None, &third)
+            .await
+            .unwrap();
+
+        store.revoke_family(&first).await.unwrap();
+        assert_eq!(
+            store
+                .rotate_token(&first, None, &RefreshToken::default())
+                .await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+
+        store.revoke_all(&email()).await.unwrap();
+        assert!(store.tokens.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_family_keeps_client() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let session = RefreshToken::default();
+        let token = RefreshToken::default();
+        let next = RefreshToken::default();
+        let client = RefreshTokenClient {
+            client_id: "test-client".to_owned(),
+            scope: Some("openid".to_owned()),
+        };
+
+        store
+            .add_token(email(), authentication(), None, &session)
+            .await
+            .unwrap();
+        store
+            .add_token(email(), authentication(), Some(client.clone()), &token)
+            .await
+            .unwrap();
+        store
+            .rotate_token(&token, Some("test-client"), &next)
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_client(&session).await, Ok(None));
+        assert_eq!(store.get_client(&next).await, Ok(Some(client)));
+        assert_eq!(
+            store.get_client(&RefreshToken::default()).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rotate_token_of_another_client() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let login = RefreshToken::default();
+        let token = RefreshToken::default();
+        let client = RefreshTokenClient {
+            client_id: "test-client".to_owned(),
+            scope: None,
+        };
+
+        store
+            .add_token(email(), authentication(), None, &login)
+            .await
+            .unwrap();
+        store
+            .add_token(email(), authentication(), Some(client), &token)
+            .await
+            .unwrap();
+
+        for (token, client_id) in [
+            (&token, None),
+            (&token, Some("other")),
+            (&login, Some("test-client")),
+        ] {
+            assert_eq!(
+                store
+                    .rotate_token(token, client_id, &RefreshToken::default())
+                    .await,
+                Err(RefreshTokenStoreError::ClientMismatch)
+            );
+        }
+        // neither family was touched
+        assert_eq!(
+            store
+                .rotate_token(&token, Some("test-client"), &RefreshToken::default())
+                .await,
+            Ok(session())
+        );
+        assert_eq!(
+            store
+                .rotate_token(&login, None, &RefreshToken::default())
+                .await,
+            Ok(session())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_family_expires_despite_refreshes() {
+        let mut store = HashmapRefreshTokenStore::new(Duration::days(14), Duration::zero());
+        let token = RefreshToken::default();
+
+        store
+            .add_token(email(), authentication(), None, &token)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store
+                .rotate_token(&token, None, &RefreshToken::default())
+                .await,
+            Err(RefreshTokenStoreError::Expired)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prune_expired() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let next = RefreshToken::default();
+
+        store
+            .add_token(email(), authentication(), None, &token)
+            .await
+            .unwrap();
+        store.rotate_token(&token, None, &next).await.unwrap();
+
+        let now = Utc::now();
+        assert_eq!(store.prune_expired(now), Ok(0));
+
+        // the rotated token was issued a day before its successor
+        store
+            .tokens
+            .lock()
+            .unwrap()
+            .get_mut(&token.hash())
+            .unwrap()
+            .created_at -= Duration::days(1);
+        assert_eq!(store.prune_expired(now + Duration::days(13)), Ok(1));
+        assert_eq!(store.prune_expired(now + Duration::days(15)), Ok(1));
+        assert!(store.tokens.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_token() {
+        let mut store = HashmapRefreshTokenStore::new(Duration::zero(), Duration::days(90));
+        let token = RefreshToken::default();
+
+        store
+            .add_token(email(), authentication(), None, &token)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store
+                .rotate_token(&token, None, &RefreshToken::default())
+                .await,
+            Err(RefreshTokenStoreError::Expired)
+        );
+    }
+}
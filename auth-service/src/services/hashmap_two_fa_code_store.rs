@@ -0,0 +1,333 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::{
+    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    utils::constants::{TWO_FA_CODE_TTL_SECONDS, TWO_FA_MAX_ATTEMPTS},
+};
+use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone)]
+pub struct PendingTwoFA {
+    pub login_attempt_id: LoginAttemptId,
+    pub code: TwoFACode,
+    pub created_at: DateTime<Utc>,
+    pub failed_attempts: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct HashmapTwoFACodeStore {
+    pub codes: Arc<Mutex<HashMap<Email, PendingTwoFA>>>,
+    ttl: Duration,
+    max_attempts: u32,
+}
+
+impl HashmapTwoFACodeStore {
+    #[must_use]
+    pub fn new(ttl: Duration, max_attempts: u32) -> Self {
+        Self {
+            codes: Arc::default(),
+            ttl,
+            max_attempts,
+        }
+    }
+
+    fn is_expired(&self, pending: &PendingTwoFA) -> bool {
+        Utc::now() >= pending.created_at + self.ttl
+    }
+}
+
+impl Default for HashmapTwoFACodeStore {
+    fn default() -> Self {
+        Self::new(
+            Duration::seconds(TWO_FA_CODE_TTL_SECONDS),
+            TWO_FA_MAX_ATTEMPTS,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for HashmapTwoFACodeStore {
+    async fn add_code(
+        &mut self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let mut codes = self
+            .codes
+            .lock()
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        // abandoned attempts are never verified, so clean them up here
+        codes.retain(|_, pending| !self.is_expired(pending));
+
+        // a new login attempt always replaces the pending one
+        codes.insert(
+            email,
+            PendingTwoFA {
+                login_attempt_id,
+                code,
+                created_at: Utc::now(),
+                failed_attempts: 0,
+            },
+        );
+        Ok(())
+    }
+
+    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        self.codes
+            .lock()
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?
+            .remove(email);
+        Ok(())
+    }
+
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let codes = self
+            .codes
+            .lock()
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        match codes.get(email) {
+            Some(pending) if self.is_expired(pending) => Err(TwoFACodeStoreError::Expired),
+            Some(pending) => Ok((pending.login_attempt_id.clone(), pending.code.clone())),
+            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        }
+    }
+
+    async fn verify_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let mut codes = self
+            .codes
+            .lock()
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        let Entry::Occupied(mut entry) = codes.entry(email.clone()) else {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        };
+
+        if self.is_expired(entry.get()) {
+            entry.remove();
+            return Err(TwoFACodeStoreError::Expired);
+        }
+
+        // only guesses for this very attempt count, anybody knowing the email could burn it otherwise
+        let pending = entry.get_mut();
+        if &pending.login_attempt_id != login_attempt_id {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+        if &pending.code == code {
+            entry.remove();
+            return Ok(());
+        }
+
+        pending.failed_attempts += 1;
+        if pending.failed_attempts >= self.max_attempts {
+            entry.remove();
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+        Err(TwoFACodeStoreError::IncorrectCode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrong_code(code: &TwoFACode) -> TwoFACode {
+        let wrong = if code.as_ref() == "000000" {
+            "111111"
+        } else {
+            "000000"
+        };
+        TwoFACode::parse(wrong).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_code() {
+        let mut storage = HashmapTwoFACodeStore::default();
+        let email = Email::parse("hnariman@gmail.com").unwrap();
+        let id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+
+        storage
+            .add_code(email.clone(), id.clone(), code.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(storage.get_code(&email).await, Ok((id, code)));
+    }
+
+    #[tokio::test]
+    async fn test_add_code_replaces_pending_attempt() {
+        let mut storage = HashmapTwoFACodeStore::default();
+        let email = Email::parse("hnariman@gmail.com").unwrap();
+        let id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+
+        storage
+            .add_code(
+                email.clone(),
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
+        storage
+            .add_code(email.clone(), id.clone(), code.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(storage.codes.lock().unwrap().len(), 1);
+        assert_eq!(storage.get_code(&email).await, Ok((id, code)));
+    }
+
+    #[tokio::test]
+    async fn test_remove_code() {
+        let mut storage = HashmapTwoFACodeStore::default();
+        let email = Email::parse("hnariman@gmail.com").unwrap();
+
+        storage
+            .add_code(
+                email.clone(),
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
+        storage.remove_code(&email).await.unwrap();
+
+        assert_eq!(
+            storage.get_code(&email).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_code_is_single_use() {
+        let mut storage = HashmapTwoFACodeStore::default();
+        let email = Email::parse("hnariman@gmail.com").unwrap();
+        let id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+
+        storage
+            .add_code(email.clone(), id.clone(), code.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(storage.verify_code(&email, &id, &code).await, Ok(()));
+        assert_eq!(
+            storage.verify_code(&email, &id, &code).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_code_rejects_other_login_attempt() {
+        let mut storage = HashmapTwoFACodeStore::new(Duration::seconds(600), 2);
+        let email = Email::parse("hnariman@gmail.com").unwrap();
+        let id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+
+        storage
+            .add_code(email.clone(), id.clone(), code.clone())
+            .await
+            .unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(
+                storage
+                    .verify_code(&email, &LoginAttemptId::default(), &code)
+                    .await,
+                Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+            );
+        }
+        // guesses for another attempt don't count against this one
+        assert_eq!(storage.verify_code(&email, &id, &code).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_verify_code_drops_attempt_after_max_failures() {
+        let mut storage = HashmapTwoFACodeStore::new(Duration::seconds(600), 2);
+        let email = Email::parse("hnariman@gmail.com").unwrap();
+        let id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+        let wrong = wrong_code(&code);
+
+        storage
+            .add_code(email.clone(), id.clone(), code.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.verify_code(&email, &id, &wrong).await,
+            Err(TwoFACodeStoreError::IncorrectCode)
+        );
+        assert_eq!(
+            storage.verify_code(&email, &id, &wrong).await,
+            Err(TwoFACodeStoreError::TooManyAttempts)
+        );
+        assert_eq!(
+            storage.verify_code(&email, &id, &code).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_code_is_rejected_and_removed() {
+        let mut storage = HashmapTwoFACodeStore::new(Duration::zero(), TWO_FA_MAX_ATTEMPTS);
+        let email = Email::parse("hnariman@gmail.com").unwrap();
+        let id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+
+        storage
+            .add_code(email.clone(), id.clone(), code.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.get_code(&email).await,
+            Err(TwoFACodeStoreError::Expired)
+        );
+        assert_eq!(
+            storage.verify_code(&email, &id, &code).await,
+            Err(TwoFACodeStoreError::Expired)
+        );
+        assert!(storage.codes.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_add_code_prunes_expired_attempts() {
+        let mut storage = HashmapTwoFACodeStore::new(Duration::zero(), TWO_FA_MAX_ATTEMPTS);
+        let first = Email::parse("hnariman@gmail.com").unwrap();
+        let second = Email::parse("h.nariman@gmail.com").unwrap();
+
+        storage
+            .add_code(first, LoginAttemptId::default(), TwoFACode::default())
+            .await
+            .unwrap();
+        storage
+            .add_code(
+                second.clone(),
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
+
+        let codes = storage.codes.lock().unwrap();
+        assert_eq!(codes.len(), 1);
+        assert!(codes.contains_key(&second));
+    }
+}
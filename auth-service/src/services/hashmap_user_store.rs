@@ -1,6 +1,9 @@
 #![warn(clippy::all, clippy::pedantic)]
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use crate::{
+    domain::{Email, HashedPassword, Password, PasswordHashError, User, UserStore, UserStoreError},
+    utils::constants::DUMMY_PASSWORD_HASH,
+};
 use std::{
     collections::{hash_map::Entry, HashMap},
     sync::{Arc, Mutex},
@@ -33,18 +36,35 @@ impl UserStore for HashmapUserStore {
     }
 
     async fn validate_user(&self, email: &str, password: &str) -> Result<(), UserStoreError> {
-        let email = Email::parse(&email)?;
-        let password = Password::parse(&password)?;
-
-        match self.users.lock().unwrap().entry(email) {
-            Entry::Occupied(u) => {
-                if password != u.get().password {
-                    Err(UserStoreError::InvalidCredentials)
-                } else {
-                    Ok(())
-                }
+        let email = Email::parse(email)?;
+        let password = Password::parse(password)?;
+
+        // don't hold the lock while hashing
+        let user = self.users.lock().unwrap().get(&email).cloned();
+        let Some(user) = user else {
+            // hash anyway, answering faster would tell the account doesn't exist
+            let _ = DUMMY_PASSWORD_HASH.verify(&password).await;
+            return Err(UserStoreError::UserNotFound);
+        };
+
+        user.password.verify(&password).await.map_err(|e| match e {
+            PasswordHashError::Mismatch => UserStoreError::InvalidCredentials,
+            e => UserStoreError::PasswordHash(e),
+        })
+    }
+
+    async fn update_password(
+        &mut self,
+        email: &str,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError> {
+        let email = Email::parse(email)?;
+        match self.users.lock().unwrap().get_mut(&email) {
+            Some(user) => {
+                user.password = password;
+                Ok(())
             }
-            Entry::Vacant(_) => Err(UserStoreError::UserNotFound),
+            None => Err(UserStoreError::UserNotFound),
         }
     }
 }
@@ -55,11 +75,18 @@ mod tests {
 
     use super::*;
 
+    async fn user(email: &str, password: &str) -> User {
+        let params = argon2::Params::new(1024, 1, 1, None).unwrap();
+        let password = Password::parse(password).unwrap();
+        let hash = HashedPassword::hash(&password, params).await.unwrap();
+        User::new(Email::parse(email).unwrap(), hash, false)
+    }
+
     #[tokio::test]
     pub async fn test_add_user() {
         let mut storage = HashmapUserStore::default();
-        let mock = User::new("hnariman@gmail.com", "123oi1u23", false).unwrap();
-        let mock2 = User::new("h.nariman@gmail.com", "123oi1u23", false).unwrap();
+        let mock = user("hnariman@gmail.com", "123oi1u23").await;
+        let mock2 = user("h.nariman@gmail.com", "123oi1u23").await;
         let _added_mock = storage.add_user(mock).await;
         let _added_mock2 = storage.add_user(mock2).await;
 
@@ -69,8 +96,8 @@ mod tests {
     #[tokio::test]
     async fn test_add_user_existing_user() {
         let mut storage = HashmapUserStore::default();
-        let mock = User::new("h.nariman@gmail.com", "123oi1u23", false).unwrap();
-        let mock2 = User::new("h.nariman@gmail.com", "123oi1u23", false).unwrap();
+        let mock = user("h.nariman@gmail.com", "123oi1u23").await;
+        let mock2 = user("h.nariman@gmail.com", "123oi1u23").await;
         let _added_mock = storage.add_user(mock).await;
         let expected = storage.add_user(mock2).await;
 
@@ -79,20 +106,20 @@ mod tests {
 
     #[tokio::test]
     pub async fn test_add_user_short_password() {
-        let expected = User::new("h.nariman@gmail.com", "123", false).map_err(|e| e);
+        let expected = Password::parse("123");
         assert_eq!(expected, Err(CreateUserError::InvalidPassword));
     }
 
     #[tokio::test]
     pub async fn test_add_user_invalid_email() {
-        let expected = User::new("h.narimangmail.com", "123", false).map_err(|e| e);
+        let expected = Email::parse("h.narimangmail.com");
         assert_eq!(expected, Err(CreateUserError::InvalidEmail));
     }
 
     #[tokio::test]
     pub async fn test_get_user() {
         let mut storage = HashmapUserStore::default();
-        let mock = User::new("tnariman@gmail.com", "123oi1u23", false).unwrap();
+        let mock = user("tnariman@gmail.com", "123oi1u23").await;
         let _added_mock = storage.add_user(mock.clone()).await;
 
         let found = storage.get_user(mock.email.as_ref()).await.unwrap();
@@ -100,10 +127,34 @@ mod tests {
         assert_eq!(found, mock);
     }
 
+    #[tokio::test]
+    pub async fn test_user_password_is_not_stored_in_plaintext() {
+        let mut storage = HashmapUserStore::default();
+        let mock = user("tnariman@gmail.com", "123oi1u23").await;
+        storage.add_user(mock.clone()).await.unwrap();
+
+        let found = storage.get_user(mock.email.as_ref()).await.unwrap();
+
+        assert!(found.password.as_ref().starts_with("$argon2id$"));
+    }
+
+    #[tokio::test]
+    pub async fn test_validate_user() {
+        let mut storage = HashmapUserStore::default();
+        let mock = user("hnariman@gmail.com", "123asdf987234").await;
+        storage.add_user(mock).await.unwrap();
+
+        let validation_result = storage
+            .validate_user("hnariman@gmail.com", "123asdf987234")
+            .await;
+
+        assert_eq!(validation_result, Ok(()));
+    }
+
     #[tokio::test]
     pub async fn test_validate_user_shall_throw_invalid_credentials() {
         let storage = HashmapUserStore::default();
-        let mock = User::new("hnariman@gmail.com", "123asdf987234", false).unwrap();
+        let mock = user("hnariman@gmail.com", "123asdf987234").await;
 
         storage
             .users
@@ -126,7 +177,7 @@ mod tests {
         let pass = "123asldkfj123";
         let storage = HashmapUserStore::default();
 
-        let mock = User::new(email, pass, false).expect("unable to create mock user for test");
+        let mock = user(email, pass).await;
 
         storage
             .users
@@ -139,4 +190,44 @@ mod tests {
 
         assert_eq!(validation_result, expected);
     }
+
+    #[tokio::test]
+    pub async fn test_update_password() {
+        let mut storage = HashmapUserStore::default();
+        let mock = user("hnariman@gmail.com", "123asdf987234").await;
+        storage.add_user(mock).await.unwrap();
+
+        let legacy = bcrypt::hash("987asdf123432", 4).unwrap();
+        storage
+            .update_password(
+                "hnariman@gmail.com",
+                HashedPassword::parse(&legacy).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage
+                .validate_user("hnariman@gmail.com", "987asdf123432")
+                .await,
+            Ok(())
+        );
+        assert_eq!(
+            storage
+                .validate_user("hnariman@gmail.com", "123asdf987234")
+                .await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_update_password_unknown_user() {
+        let mut storage = HashmapUserStore::default();
+        let hash = user("hnariman@gmail.com", "123asdf987234").await.password;
+
+        assert_eq!(
+            storage.update_password("hnariman@gmail.com", hash).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
 }
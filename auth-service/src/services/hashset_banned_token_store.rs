@@ -1,32 +1,173 @@
 #![warn(clippy::all, clippy::pedantic)]
 
-use crate::domain::{BannedTokenError, BannedTokenStore};
+use crate::{
+    domain::{BannedTokenError, BannedTokenStore},
+    utils::auth::TOKEN_TTL_SECONDS,
+};
+use chrono::Utc;
 use std::{
-    collections::HashSet,
-    sync::{Arc, Mutex},
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
 };
+use tokio::task::JoinHandle;
 
 #[derive(Debug, Default, Clone)]
 pub struct HashsetBannedTokenStore {
-    pub banned: Arc<Mutex<HashSet<String>>>,
+    // jti -> the token's `exp` claim, the entry is pruned once that has passed
+    pub banned: Arc<Mutex<HashMap<String, usize>>>,
+    // subject -> tokens issued before this timestamp are revoked
+    pub revoked_before: Arc<Mutex<HashMap<String, usize>>>,
+    pruned_total: Arc<AtomicU64>,
+}
+
+// What one pass of `prune_expired` evicted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PrunedEntries {
+    pub banned_tokens: usize,
+    pub revoked_subjects: usize,
+}
+
+// Size of the store, reported after every pruning pass
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BannedTokenStoreMetrics {
+    pub banned_tokens: usize,
+    pub revoked_subjects: usize,
+    // entries evicted since the store was created
+    pub pruned_total: u64,
+}
+
+impl HashsetBannedTokenStore {
+    /// Drops the entries that can't reject any token anymore, returns how many were removed.
+    ///
+    /// # Errors
+    /// When a lock is poisoned.
+    pub fn prune_expired(&self, now: usize) -> Result<PrunedEntries, BannedTokenError> {
+        let banned_tokens = {
+            let mut banned = self.banned.lock().map_err(|_| BannedTokenError::Poisoned)?;
+            let before = banned.len();
+            banned.retain(|_, expires_at| *expires_at > now);
+            before - banned.len()
+        };
+
+        // every token issued before the cutoff has expired by now
+        let ttl =
+            usize::try_from(TOKEN_TTL_SECONDS).map_err(|_| BannedTokenError::UnexpectedError)?;
+        let mut revoked = self
+            .revoked_before
+            .lock()
+            .map_err(|_| BannedTokenError::Poisoned)?;
+        let before = revoked.len();
+        revoked.retain(|_, cutoff| cutoff.saturating_add(ttl) > now);
+
+        let pruned = PrunedEntries {
+            banned_tokens,
+            revoked_subjects: before - revoked.len(),
+        };
+        self.pruned_total.fetch_add(
+            (pruned.banned_tokens + pruned.revoked_subjects) as u64,
+            Ordering::Relaxed,
+        );
+        Ok(pruned)
+    }
+
+    /// # Errors
+    /// When a lock is poisoned.
+    pub fn metrics(&self) -> Result<BannedTokenStoreMetrics, BannedTokenError> {
+        Ok(BannedTokenStoreMetrics {
+            banned_tokens: self
+                .banned
+                .lock()
+                .map_err(|_| BannedTokenError::Poisoned)?
+                .len(),
+            revoked_subjects: self
+                .revoked_before
+                .lock()
+                .map_err(|_| BannedTokenError::Poisoned)?
+                .len(),
+            pruned_total: self.pruned_total.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Evicts expired entries in the background every `period`, the task runs until aborted.
+    #[must_use]
+    pub fn spawn_pruning(&self, period: Duration) -> JoinHandle<()> {
+        let store = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                let now = usize::try_from(Utc::now().timestamp()).unwrap_or_default();
+
+                match store
+                    .prune_expired(now)
+                    .and_then(|pruned| Ok((pruned, store.metrics()?)))
+                {
+                    Ok((pruned, _)) if pruned == PrunedEntries::default() => {}
+                    Ok((pruned, metrics)) => eprintln!(
+                        "banned token store: pruned {} tokens and {} subject cutoffs, {} tokens and {} subject cutoffs left, {} pruned in total",
+                        pruned.banned_tokens,
+                        pruned.revoked_subjects,
+                        metrics.banned_tokens,
+                        metrics.revoked_subjects,
+                        metrics.pruned_total
+                    ),
+                    Err(e) => eprintln!("unable to prune banned tokens: {e}"),
+                }
+            }
+        })
+    }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add(&mut self, _data: String) -> Result<(), BannedTokenError> {
+    async fn add(&mut self, jti: String, expires_at: usize) -> Result<(), BannedTokenError> {
         let mut banned = self.banned.lock().map_err(|_| BannedTokenError::Poisoned)?;
-        banned.insert(_data);
+        banned.insert(jti, expires_at);
         Ok(())
     }
 
-    async fn check(&self, _data: String) -> Result<(), BannedTokenError> {
-        let data = self.banned.lock().map_err(|_| BannedTokenError::Poisoned)?;
+    async fn check(&self, jti: String) -> Result<(), BannedTokenError> {
+        let banned = self.banned.lock().map_err(|_| BannedTokenError::Poisoned)?;
 
-        if data.get(_data.as_str()).is_some() {
+        if banned.contains_key(jti.as_str()) {
             return Err(BannedTokenError::BannedToken);
         }
         Ok(())
     }
+
+    async fn revoke_issued_before(
+        &mut self,
+        subject: String,
+        issued_before: usize,
+    ) -> Result<(), BannedTokenError> {
+        let mut revoked = self
+            .revoked_before
+            .lock()
+            .map_err(|_| BannedTokenError::Poisoned)?;
+
+        let cutoff = revoked.entry(subject).or_default();
+        *cutoff = (*cutoff).max(issued_before);
+        Ok(())
+    }
+
+    async fn check_issued(&self, subject: &str, issued_at: usize) -> Result<(), BannedTokenError> {
+        let revoked = self
+            .revoked_before
+            .lock()
+            .map_err(|_| BannedTokenError::Poisoned)?;
+
+        match revoked.get(subject) {
+            Some(cutoff) if issued_at < *cutoff => Err(BannedTokenError::BannedToken),
+            _ => Ok(()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -39,8 +180,14 @@ mod tests {
     pub async fn test_add_token() {
         let mut storage = HashsetBannedTokenStore::default();
 
-        let _ = storage.add(String::from("asldkfjasl;dkj")).await.unwrap();
-        let _ = storage.add(String::from("woeiruowieulas")).await.unwrap();
+        storage
+            .add(String::from("asldkfjasl;dkj"), 1_000)
+            .await
+            .unwrap();
+        storage
+            .add(String::from("woeiruowieulas"), 1_000)
+            .await
+            .unwrap();
 
         assert_eq!(storage.banned.lock().unwrap().len(), 2);
     }
@@ -50,11 +197,90 @@ mod tests {
         let mut storage = HashsetBannedTokenStore::default();
         let token = String::from("asldkfjalsdkjf");
 
-        let _ = storage.add(token.clone()).await.unwrap();
+        storage.add(token.clone(), 1_000).await.unwrap();
 
         assert_eq!(
             storage.check(token).await,
             Err(BannedTokenError::BannedToken)
-        )
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_revoke_issued_before() {
+        let mut storage = HashsetBannedTokenStore::default();
+        let subject = String::from("test@example.com");
+
+        storage
+            .revoke_issued_before(subject.clone(), 1_000)
+            .await
+            .unwrap();
+        // an older cutoff never restores revoked tokens
+        storage
+            .revoke_issued_before(subject.clone(), 500)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.check_issued(&subject, 999).await,
+            Err(BannedTokenError::BannedToken)
+        );
+        assert_eq!(storage.check_issued(&subject, 1_000).await, Ok(()));
+        assert_eq!(storage.check_issued("other@example.com", 999).await, Ok(()));
+    }
+
+    #[tokio::test]
+    pub async fn test_prune_expired() {
+        let mut storage = HashsetBannedTokenStore::default();
+        let ttl = usize::try_from(TOKEN_TTL_SECONDS).unwrap();
+
+        storage.add(String::from("expired"), 1_000).await.unwrap();
+        storage.add(String::from("valid"), 2_000).await.unwrap();
+        storage
+            .revoke_issued_before(String::from("old@example.com"), 1_000 - ttl)
+            .await
+            .unwrap();
+        storage
+            .revoke_issued_before(String::from("new@example.com"), 1_500)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.prune_expired(1_000),
+            Ok(PrunedEntries {
+                banned_tokens: 1,
+                revoked_subjects: 1,
+            })
+        );
+
+        assert_eq!(storage.check(String::from("expired")).await, Ok(()));
+        assert_eq!(
+            storage.check(String::from("valid")).await,
+            Err(BannedTokenError::BannedToken)
+        );
+        assert_eq!(
+            storage.check_issued("new@example.com", 1_499).await,
+            Err(BannedTokenError::BannedToken)
+        );
+        assert_eq!(
+            storage.metrics(),
+            Ok(BannedTokenStoreMetrics {
+                banned_tokens: 1,
+                revoked_subjects: 1,
+                pruned_total: 2,
+            })
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_spawn_pruning() {
+        let mut storage = HashsetBannedTokenStore::default();
+        storage.add(String::from("expired"), 1).await.unwrap();
+
+        let task = storage.spawn_pruning(Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        task.abort();
+
+        assert_eq!(storage.metrics().unwrap().banned_tokens, 0);
+        assert_eq!(storage.metrics().unwrap().pruned_total, 1);
     }
 }
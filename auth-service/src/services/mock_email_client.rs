@@ -0,0 +1,76 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::domain::{Email, EmailClient, EmailClientError, EmailMessage};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentEmail {
+    pub recipient: Email,
+    pub message: EmailMessage,
+}
+
+// Keeps every message in memory so tests can read them back
+#[derive(Debug, Default, Clone)]
+pub struct MockEmailClient {
+    pub sent: Arc<Mutex<Vec<SentEmail>>>,
+}
+
+impl MockEmailClient {
+    /// Most recent message sent to `recipient`, if any.
+    #[must_use]
+    pub fn last_sent_to(&self, recipient: &Email) -> Option<SentEmail> {
+        self.sent
+            .lock()
+            .ok()?
+            .iter()
+            .rev()
+            .find(|email| &email.recipient == recipient)
+            .cloned()
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for MockEmailClient {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<(), EmailClientError> {
+        self.sent
+            .lock()
+            .map_err(|e| EmailClientError::DeliveryFailed(e.to_string()))?
+            .push(SentEmail {
+                recipient: recipient.clone(),
+                message: message.clone(),
+            });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_email_is_recorded() {
+        let client = MockEmailClient::default();
+        let first = Email::parse("hnariman@gmail.com").unwrap();
+        let second = Email::parse("h.nariman@gmail.com").unwrap();
+
+        let message = |text: &str| EmailMessage {
+            subject: "hello".to_owned(),
+            text: text.to_owned(),
+            html: format!("<p>{text}</p>"),
+        };
+
+        client.send_email(&first, &message("one")).await.unwrap();
+        client.send_email(&second, &message("two")).await.unwrap();
+        client.send_email(&first, &message("three")).await.unwrap();
+
+        assert_eq!(client.sent.lock().unwrap().len(), 3);
+        assert_eq!(
+            client.last_sent_to(&first).map(|email| email.message),
+            Some(message("three"))
+        );
+    }
+}
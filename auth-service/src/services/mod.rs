@@ -2,3 +2,31 @@ pub mod hashmap_user_store;
 pub use hashmap_user_store::*;
 pub mod hashset_banned_token_store;
 pub use hashset_banned_token_store::*;
+pub mod hashmap_two_fa_code_store;
+pub use hashmap_two_fa_code_store::*;
+pub mod mock_email_client;
+pub use mock_email_client::*;
+pub mod file_email_client;
+pub use file_email_client::*;
+pub mod smtp_email_client;
+pub use smtp_email_client::*;
+pub mod hashmap_password_reset_token_store;
+pub use hashmap_password_reset_token_store::*;
+pub mod hashmap_refresh_token_store;
+pub use hashmap_refresh_token_store::*;
+pub mod hashmap_client_store;
+pub use hashmap_client_store::*;
+pub mod hashmap_authorization_code_store;
+pub use hashmap_authorization_code_store::*;
+pub mod hashmap_device_code_store;
+pub use hashmap_device_code_store::*;
+pub mod sqlite_user_store;
+pub use sqlite_user_store::*;
+pub mod postgres;
+pub use postgres::*;
+pub mod postgres_user_store;
+pub use postgres_user_store::*;
+pub mod postgres_banned_token_store;
+pub use postgres_banned_token_store::*;
+pub mod redis_banned_token_store;
+pub use redis_banned_token_store::*;
@@ -0,0 +1,91 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::time::Duration;
+
+use sqlx::{
+    migrate::Migrator,
+    postgres::{PgPool, PgPoolOptions},
+};
+
+use crate::utils::constants::{
+    env, env_optional, DATABASE_MAX_CONNECTIONS, DATABASE_URL, DEFAULT_DATABASE_MAX_CONNECTIONS,
+};
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+// Connection pool settings, shared by every Postgres backed store
+#[derive(Debug, Clone)]
+pub struct PostgresConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+}
+
+impl PostgresConfig {
+    #[must_use]
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_owned(),
+            max_connections: DEFAULT_DATABASE_MAX_CONNECTIONS,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Postgres is used when `DATABASE_URL` points to it, `None` otherwise.
+    /// # Panics
+    /// On invalid pool settings.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        if !(DATABASE_URL.starts_with("postgres://") || DATABASE_URL.starts_with("postgresql://")) {
+            return None;
+        }
+        let mut config = Self::new(&DATABASE_URL);
+        config.max_connections = *DATABASE_MAX_CONNECTIONS;
+
+        if let Some(min) = env_optional(env::DATABASE_MIN_CONNECTIONS_ENV_VAR) {
+            config.min_connections = min
+                .parse()
+                .expect("DATABASE_MIN_CONNECTIONS must be a number.");
+        }
+        if let Some(timeout) = env_optional(env::DATABASE_ACQUIRE_TIMEOUT_ENV_VAR) {
+            config.acquire_timeout = Duration::from_secs(
+                timeout
+                    .parse()
+                    .expect("DATABASE_ACQUIRE_TIMEOUT_SECONDS must be a number."),
+            );
+        }
+        Some(config)
+    }
+
+    /// Open the pool and run pending migrations.
+    /// # Errors
+    /// When the database can't be reached or a migration fails.
+    pub async fn connect(&self) -> Result<PgPool, sqlx::Error> {
+        let pool = PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(self.acquire_timeout)
+            .connect(&self.url)
+            .await?;
+
+        MIGRATOR.run(&pool).await?;
+        Ok(pool)
+    }
+}
+
+// Pool shared by the Postgres store tests, runs against `TEST_DATABASE_URL`
+#[cfg(all(test, feature = "postgres-tests"))]
+pub(crate) async fn test_pool() -> PgPool {
+    dotenvy::dotenv().ok();
+    let url = std::env::var("TEST_DATABASE_URL")
+        .unwrap_or_else(|_| "postgres://postgres@localhost:5432/postgres".to_owned());
+
+    let mut config = PostgresConfig::new(&url);
+    config.max_connections = 2;
+    config
+        .connect()
+        .await
+        .expect("Failed to connect to test database")
+}
@@ -0,0 +1,129 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use sqlx::postgres::PgPool;
+
+use crate::domain::{BannedTokenError, BannedTokenStore};
+
+#[derive(Debug, Clone)]
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresBannedTokenStore {
+    /// The pool comes from `PostgresConfig::connect`, which runs the migrations.
+    #[must_use]
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn database_error(e: &sqlx::Error) -> BannedTokenError {
+    BannedTokenError::DatabaseError(e.to_string())
+}
+
+fn timestamp(seconds: usize) -> Result<i64, BannedTokenError> {
+    i64::try_from(seconds).map_err(|_| BannedTokenError::InvalidInput)
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    async fn add(&mut self, jti: String, expires_at: usize) -> Result<(), BannedTokenError> {
+        sqlx::query(
+            "INSERT INTO banned_tokens (jti, expires_at) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(jti)
+        .bind(timestamp(expires_at)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| database_error(&e))?;
+        Ok(())
+    }
+
+    async fn check(&self, jti: String) -> Result<(), BannedTokenError> {
+        let banned: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM banned_tokens WHERE jti = $1)")
+                .bind(jti)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| database_error(&e))?;
+
+        if banned {
+            return Err(BannedTokenError::BannedToken);
+        }
+        Ok(())
+    }
+
+    async fn revoke_issued_before(
+        &mut self,
+        subject: String,
+        issued_before: usize,
+    ) -> Result<(), BannedTokenError> {
+        sqlx::query(
+            "INSERT INTO revoked_sessions (subject, issued_before) VALUES ($1, $2) \
+             ON CONFLICT (subject) DO UPDATE \
+             SET issued_before = GREATEST(revoked_sessions.issued_before, EXCLUDED.issued_before)",
+        )
+        .bind(subject)
+        .bind(timestamp(issued_before)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| database_error(&e))?;
+        Ok(())
+    }
+
+    async fn check_issued(&self, subject: &str, issued_at: usize) -> Result<(), BannedTokenError> {
+        let cutoff: Option<i64> =
+            sqlx::query_scalar("SELECT issued_before FROM revoked_sessions WHERE subject = $1")
+                .bind(subject)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| database_error(&e))?;
+
+        match cutoff {
+            Some(cutoff) if timestamp(issued_at)? < cutoff => Err(BannedTokenError::BannedToken),
+            _ => Ok(()),
+        }
+    }
+}
+
+// cargo test --features postgres-tests, with TEST_DATABASE_URL pointing to a scratch database
+#[cfg(all(test, feature = "postgres-tests"))]
+mod tests {
+    use super::*;
+    use crate::services::postgres::test_pool;
+
+    #[tokio::test]
+    async fn test_add_check() {
+        let mut storage = PostgresBannedTokenStore::new(test_pool().await);
+        let jti = uuid::Uuid::new_v4().to_string();
+
+        assert_eq!(storage.check(jti.clone()).await, Ok(()));
+        storage.add(jti.clone(), 0).await.unwrap();
+        // banning twice is fine
+        storage.add(jti.clone(), 0).await.unwrap();
+
+        assert_eq!(storage.check(jti).await, Err(BannedTokenError::BannedToken));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_issued_before() {
+        let mut storage = PostgresBannedTokenStore::new(test_pool().await);
+        let subject = format!("{}@example.com", uuid::Uuid::new_v4());
+
+        storage
+            .revoke_issued_before(subject.clone(), 1_000)
+            .await
+            .unwrap();
+        storage
+            .revoke_issued_before(subject.clone(), 500)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.check_issued(&subject, 999).await,
+            Err(BannedTokenError::BannedToken)
+        );
+        assert_eq!(storage.check_issued(&subject, 1_000).await, Ok(()));
+        assert_eq!(storage.check_issued("other@example.com", 999).await, Ok(()));
+    }
+}
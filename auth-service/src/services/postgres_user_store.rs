@@ -0,0 +1,171 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use sqlx::{postgres::PgPool, Row};
+
+use crate::{
+    domain::{Email, HashedPassword, Password, PasswordHashError, User, UserStore, UserStoreError},
+    utils::constants::DUMMY_PASSWORD_HASH,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresUserStore {
+    pool: PgPool,
+}
+
+impl PostgresUserStore {
+    /// The pool comes from `PostgresConfig::connect`, which runs the migrations.
+    #[must_use]
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn database_error(e: &sqlx::Error) -> UserStoreError {
+    match e {
+        sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
+        sqlx::Error::Database(db) if db.is_unique_violation() => UserStoreError::UserAlreadyExists,
+        e => UserStoreError::DatabaseError(e.to_string()),
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for PostgresUserStore {
+    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        sqlx::query("INSERT INTO users (email, password_hash, requires_2fa) VALUES ($1, $2, $3)")
+            .bind(user.email.as_ref())
+            .bind(user.password.as_ref())
+            .bind(user.requires_2fa)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| database_error(&e))?;
+        Ok(())
+    }
+
+    async fn get_user(&self, email: &str) -> Result<User, UserStoreError> {
+        let email = Email::parse(email)?;
+
+        let row = sqlx::query("SELECT password_hash, requires_2fa FROM users WHERE email = $1")
+            .bind(email.as_ref())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| database_error(&e))?;
+
+        let password = HashedPassword::parse(row.get("password_hash"))
+            .map_err(UserStoreError::PasswordHash)?;
+        Ok(User::new(email, password, row.get("requires_2fa")))
+    }
+
+    async fn validate_user(&self, email: &str, password: &str) -> Result<(), UserStoreError> {
+        let password = Password::parse(password)?;
+        let user = match self.get_user(email).await {
+            Err(UserStoreError::UserNotFound) => {
+                // hash anyway, answering faster would tell the account doesn't exist
+                let _ = DUMMY_PASSWORD_HASH.verify(&password).await;
+                return Err(UserStoreError::UserNotFound);
+            }
+            result => result?,
+        };
+
+        user.password.verify(&password).await.map_err(|e| match e {
+            PasswordHashError::Mismatch => UserStoreError::InvalidCredentials,
+            e => UserStoreError::PasswordHash(e),
+        })
+    }
+
+    async fn update_password(
+        &mut self,
+        email: &str,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError> {
+        let email = Email::parse(email)?;
+
+        let result = sqlx::query("UPDATE users SET password_hash = $1 WHERE email = $2")
+            .bind(password.as_ref())
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| database_error(&e))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+}
+
+// cargo test --features postgres-tests, with TEST_DATABASE_URL pointing to a scratch database
+#[cfg(all(test, feature = "postgres-tests"))]
+mod tests {
+    use super::*;
+    use crate::services::postgres::test_pool;
+
+    async fn user(password: &str) -> User {
+        let params = argon2::Params::new(1024, 1, 1, None).unwrap();
+        let password = Password::parse(password).unwrap();
+        let hash = HashedPassword::hash(&password, params).await.unwrap();
+        let email = format!("{}@example.com", uuid::Uuid::new_v4());
+        User::new(Email::parse(&email).unwrap(), hash, false)
+    }
+
+    #[tokio::test]
+    async fn test_add_user_existing_user() {
+        let mut storage = PostgresUserStore::new(test_pool().await);
+        let mock = user("123oi1u23").await;
+        storage.add_user(mock.clone()).await.unwrap();
+
+        assert_eq!(
+            storage.add_user(mock).await,
+            Err(UserStoreError::UserAlreadyExists)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_user() {
+        let mut storage = PostgresUserStore::new(test_pool().await);
+        let mut mock = user("123oi1u23").await;
+        mock.requires_2fa = true;
+        storage.add_user(mock.clone()).await.unwrap();
+
+        assert_eq!(storage.get_user(mock.email.as_ref()).await, Ok(mock));
+        assert_eq!(
+            storage.get_user("nobody@example.com").await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_user() {
+        let mut storage = PostgresUserStore::new(test_pool().await);
+        let mock = user("123asdf987234").await;
+        let email = mock.email.as_ref().to_owned();
+        storage.add_user(mock).await.unwrap();
+
+        assert_eq!(storage.validate_user(&email, "123asdf987234").await, Ok(()));
+        assert_eq!(
+            storage.validate_user(&email, "123asdf98723").await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let mut storage = PostgresUserStore::new(test_pool().await);
+        let mock = user("123asdf987234").await;
+        let email = mock.email.as_ref().to_owned();
+        storage.add_user(mock).await.unwrap();
+
+        let legacy = bcrypt::hash("987asdf123432", 4).unwrap();
+        storage
+            .update_password(&email, HashedPassword::parse(&legacy).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(storage.validate_user(&email, "987asdf123432").await, Ok(()));
+        assert_eq!(
+            storage
+                .update_password("nobody@example.com", user("123asdf987234").await.password)
+                .await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+}
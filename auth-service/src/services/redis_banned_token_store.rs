@@ -0,0 +1,309 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use chrono::Utc;
+use redis::{aio::ConnectionManager, AsyncCommands};
+
+use crate::{
+    domain::{BannedTokenError, BannedTokenStore},
+    utils::auth::TOKEN_TTL_SECONDS,
+};
+
+// Works with any server speaking RESP (Redis, Valkey, KeyDB, Dragonfly...).
+// Every entry expires by itself once the token it bans would have expired anyway.
+#[derive(Clone)]
+pub struct RedisBannedTokenStore {
+    connection: ConnectionManager,
+}
+
+impl RedisBannedTokenStore {
+    /// # Errors
+    /// When the URL is invalid or the server can't be reached.
+    pub async fn connect(url: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+        Ok(Self { connection })
+    }
+}
+
+fn banned_jti_key(jti: &str) -> String {
+    format!("banned_jti:{jti}")
+}
+
+// Moves the cutoff of KEYS[1] to ARGV[1] unless it already is later, like `GREATEST` in Postgres.
+// A script runs atomically, two revocations racing can't put an older cutoff back.
+const RAISE_CUTOFF_SCRIPT: &str = r"
+local current = tonumber(redis.call('GET', KEYS[1]))
+if current and current >= tonumber(ARGV[1]) then
+    return 0
+end
+redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+return 1
+";
+
+fn revoked_before_key(subject: &str) -> String {
+    format!("revoked_before:{subject}")
+}
+
+fn redis_error(e: &redis::RedisError) -> BannedTokenError {
+    BannedTokenError::DatabaseError(e.to_string())
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for RedisBannedTokenStore {
+    async fn add(&mut self, jti: String, expires_at: usize) -> Result<(), BannedTokenError> {
+        let now =
+            u64::try_from(Utc::now().timestamp()).map_err(|_| BannedTokenError::UnexpectedError)?;
+        let expires_at = u64::try_from(expires_at).map_err(|_| BannedTokenError::InvalidInput)?;
+
+        // an expired token is rejected anyway
+        if expires_at <= now {
+            return Ok(());
+        }
+
+        self.connection
+            .set_ex::<_, _, ()>(banned_jti_key(&jti), 1, expires_at - now)
+            .await
+            .map_err(|e| redis_error(&e))
+    }
+
+    async fn check(&self, jti: String) -> Result<(), BannedTokenError> {
+        let banned: bool = self
+            .connection
+            .clone()
+            .exists(banned_jti_key(&jti))
+            .await
+            .map_err(|e| redis_error(&e))?;
+
+        if banned {
+            return Err(BannedTokenError::BannedToken);
+        }
+        Ok(())
+    }
+
+    async fn revoke_issued_before(
+        &mut self,
+        subject: String,
+        issued_before: usize,
+    ) -> Result<(), BannedTokenError> {
+        // tokens issued before the cutoff are all expired after one token lifetime
+        let ttl =
+            u64::try_from(TOKEN_TTL_SECONDS).map_err(|_| BannedTokenError::UnexpectedError)?;
+
+        redis::cmd("EVAL")
+            .arg(RAISE_CUTOFF_SCRIPT)
+            .arg(1)
+            .arg(revoked_before_key(&subject))
+            .arg(issued_before)
+            .arg(ttl)
+            .exec_async(&mut self.connection)
+            .await
+            .map_err(|e| redis_error(&e))
+    }
+
+    async fn check_issued(&self, subject: &str, issued_at: usize) -> Result<(), BannedTokenError> {
+        let cutoff: Option<usize> = self
+            .connection
+            .clone()
+            .get(revoked_before_key(subject))
+            .await
+            .map_err(|e| redis_error(&e))?;
+
+        match cutoff {
+            Some(cutoff) if issued_at < cutoff => Err(BannedTokenError::BannedToken),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+
+    type Entries = Arc<Mutex<HashMap<String, (String, Option<Instant>)>>>;
+
+    // Just enough of RESP2 for the store: SET [EX], SETEX, GET, EXISTS, TTL,
+    // and EVAL of the one script the store runs
+    struct RespStandIn {
+        url: String,
+        entries: Entries,
+    }
+
+    impl RespStandIn {
+        async fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("redis://{}", listener.local_addr().unwrap());
+            let entries = Entries::default();
+
+            let shared = entries.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(Self::serve(stream, shared.clone()));
+                }
+            });
+
+            Self { url, entries }
+        }
+
+        async fn serve(stream: TcpStream, entries: Entries) {
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+
+            while let Some(command) = Self::read_command(&mut reader).await {
+                let reply = Self::execute(&command, &entries);
+                if writer.write_all(reply.as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        async fn read_command(
+            reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+        ) -> Option<Vec<String>> {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.ok().filter(|n| *n > 0)?;
+            let count: usize = line.trim().strip_prefix('*')?.parse().ok()?;
+
+            let mut args = Vec::with_capacity(count);
+            for _ in 0..count {
+                line.clear();
+                reader.read_line(&mut line).await.ok()?;
+                let len: usize = line.trim().strip_prefix('$')?.parse().ok()?;
+
+                let mut arg = vec![0; len + 2];
+                reader.read_exact(&mut arg).await.ok()?;
+                arg.truncate(len);
+                args.push(String::from_utf8(arg).ok()?);
+            }
+            Some(args)
+        }
+
+        fn execute(command: &[String], entries: &Entries) -> String {
+            let mut entries = entries.lock().unwrap();
+            entries.retain(|_, (_, expiry)| expiry.is_none_or(|expiry| expiry > Instant::now()));
+
+            match command[0].to_uppercase().as_str() {
+                "SET" => {
+                    let expiry = match command.get(3).map(|arg| arg.to_uppercase()) {
+                        Some(option) if option == "EX" => {
+                            let seconds = command[4].parse().unwrap();
+                            Some(Instant::now() + Duration::from_secs(seconds))
+                        }
+                        _ => None,
+                    };
+                    entries.insert(command[1].clone(), (command[2].clone(), expiry));
+                    "+OK\r\n".to_owned()
+                }
+                "SETEX" => {
+                    let expiry = Instant::now() + Duration::from_secs(command[2].parse().unwrap());
+                    entries.insert(command[1].clone(), (command[3].clone(), Some(expiry)));
+                    "+OK\r\n".to_owned()
+                }
+                "EVAL" if command[1] == RAISE_CUTOFF_SCRIPT => {
+                    let cutoff: u64 = command[4].parse().unwrap();
+                    let current = entries
+                        .get(&command[3])
+                        .and_then(|(value, _)| value.parse::<u64>().ok());
+                    if current.is_some_and(|current| current >= cutoff) {
+                        return ":0\r\n".to_owned();
+                    }
+                    let expiry = Instant::now() + Duration::from_secs(command[5].parse().unwrap());
+                    entries.insert(command[3].clone(), (command[4].clone(), Some(expiry)));
+                    ":1\r\n".to_owned()
+                }
+                "GET" => match entries.get(&command[1]) {
+                    Some((value, _)) => format!("${}\r\n{value}\r\n", value.len()),
+                    None => "$-1\r\n".to_owned(),
+                },
+                "EXISTS" => format!(":{}\r\n", i32::from(entries.contains_key(&command[1]))),
+                "TTL" => match entries.get(&command[1]) {
+                    Some((_, Some(expiry))) => {
+                        let ttl = expiry.duration_since(Instant::now()).as_secs_f64().round();
+                        format!(":{ttl}\r\n")
+                    }
+                    Some((_, None)) => ":-1\r\n".to_owned(),
+                    None => ":-2\r\n".to_owned(),
+                },
+                // handshake, e.g. CLIENT SETINFO
+                _ => "+OK\r\n".to_owned(),
+            }
+        }
+    }
+
+    fn now() -> usize {
+        usize::try_from(Utc::now().timestamp()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_check() {
+        let server = RespStandIn::start().await;
+        let mut storage = RedisBannedTokenStore::connect(&server.url).await.unwrap();
+        let jti = uuid::Uuid::new_v4().to_string();
+
+        assert_eq!(storage.check(jti.clone()).await, Ok(()));
+        storage.add(jti.clone(), now() + 60).await.unwrap();
+
+        assert_eq!(storage.check(jti).await, Err(BannedTokenError::BannedToken));
+    }
+
+    #[tokio::test]
+    async fn test_add_expires_with_token() {
+        let server = RespStandIn::start().await;
+        let mut storage = RedisBannedTokenStore::connect(&server.url).await.unwrap();
+
+        let jti = uuid::Uuid::new_v4().to_string();
+
+        storage.add(jti.clone(), now() + 600).await.unwrap();
+
+        let ttl: i64 = redis::cmd("TTL")
+            .arg(format!("banned_jti:{jti}"))
+            .query_async(&mut storage.connection)
+            .await
+            .unwrap();
+        assert!((598..=600).contains(&ttl), "unexpected ttl {ttl}");
+    }
+
+    #[tokio::test]
+    async fn test_add_skips_expired_token() {
+        let server = RespStandIn::start().await;
+        let mut storage = RedisBannedTokenStore::connect(&server.url).await.unwrap();
+
+        storage.add(String::from("token"), now() - 1).await.unwrap();
+
+        assert!(server.entries.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_issued_before() {
+        let server = RespStandIn::start().await;
+        let mut storage = RedisBannedTokenStore::connect(&server.url).await.unwrap();
+        let subject = String::from("test@example.com");
+
+        storage
+            .revoke_issued_before(subject.clone(), 1_000)
+            .await
+            .unwrap();
+        // an older cutoff never restores revoked tokens
+        storage
+            .revoke_issued_before(subject.clone(), 500)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.check_issued(&subject, 999).await,
+            Err(BannedTokenError::BannedToken)
+        );
+        assert_eq!(storage.check_issued(&subject, 1_000).await, Ok(()));
+        assert_eq!(storage.check_issued("other@example.com", 999).await, Ok(()));
+    }
+}
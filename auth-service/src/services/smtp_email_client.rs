@@ -0,0 +1,357 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::{
+    domain::{Email, EmailClient, EmailClientError, EmailMessage},
+    utils::{
+        constants::{env, env_optional},
+        email::to_mime_message,
+    },
+};
+use lettre::{
+    transport::smtp::{authentication::Credentials, PoolConfig},
+    AsyncSmtpTransport, AsyncTransport, Tokio1Executor,
+};
+use std::{str::FromStr, time::Duration};
+
+// Retries never wait longer than this, however many of them are configured
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    // plain text, only meant for a relay on localhost or in tests
+    None,
+    // plain connection upgraded with STARTTLS, port 587
+    StartTls,
+    // TLS from the first byte, port 465
+    Implicit,
+}
+
+impl FromStr for SmtpTls {
+    type Err = EmailClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "starttls" => Ok(Self::StartTls),
+            "tls" | "implicit" => Ok(Self::Implicit),
+            other => Err(EmailClientError::InvalidConfig(format!(
+                "unknown SMTP TLS mode: {other}"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub credentials: Option<(String, String)>,
+    pub tls: SmtpTls,
+    pub pool_size: u32,
+    pub timeout: Duration,
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
+}
+
+impl SmtpConfig {
+    #[must_use]
+    pub fn new(host: &str, tls: SmtpTls) -> Self {
+        Self {
+            host: host.to_owned(),
+            port: None,
+            credentials: None,
+            tls,
+            pool_size: 4,
+            timeout: Duration::from_secs(10),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+        }
+    }
+
+    /// Reads the SMTP settings from the environment, `None` when `SMTP_HOST` isn't set.
+    ///
+    /// # Panics
+    /// When one of the optional settings is present but can't be parsed.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let host = env_optional(env::SMTP_HOST_ENV_VAR)?;
+        let tls = env_optional(env::SMTP_TLS_ENV_VAR).map_or(SmtpTls::StartTls, |tls| {
+            tls.parse()
+                .expect("SMTP_TLS must be none, starttls or tls.")
+        });
+        let mut config = Self::new(&host, tls);
+
+        config.port = env_optional(env::SMTP_PORT_ENV_VAR)
+            .map(|port| port.parse().expect("SMTP_PORT must be a port number."));
+        config.credentials =
+            env_optional(env::SMTP_USERNAME_ENV_VAR).zip(env_optional(env::SMTP_PASSWORD_ENV_VAR));
+        if let Some(size) = env_optional(env::SMTP_POOL_SIZE_ENV_VAR) {
+            config.pool_size = size.parse().expect("SMTP_POOL_SIZE must be a number.");
+        }
+        if let Some(retries) = env_optional(env::SMTP_MAX_RETRIES_ENV_VAR) {
+            config.max_retries = retries.parse().expect("SMTP_MAX_RETRIES must be a number.");
+        }
+        Some(config)
+    }
+}
+
+// Sends mail through an SMTP relay over a pool of reusable connections
+#[derive(Clone)]
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: String,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+impl SmtpEmailClient {
+    /// # Errors
+    /// When the relay host can't be used for the requested TLS mode.
+    pub fn new(config: SmtpConfig, sender: &str) -> Result<Self, EmailClientError> {
+        let invalid = |e: lettre::transport::smtp::Error| {
+            EmailClientError::InvalidConfig(format!("invalid SMTP relay: {e}"))
+        };
+
+        let mut builder = match config.tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                .map_err(invalid)?,
+            SmtpTls::Implicit => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host).map_err(invalid)?
+            }
+        };
+
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let Some((username, password)) = config.credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        let transport = builder
+            .timeout(Some(config.timeout))
+            .pool_config(PoolConfig::new().max_size(config.pool_size))
+            .build();
+
+        Ok(Self {
+            transport,
+            sender: sender.to_owned(),
+            max_retries: config.max_retries,
+            retry_backoff: config.retry_backoff,
+        })
+    }
+
+    // Doubles with every attempt, up to `MAX_RETRY_BACKOFF`
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        self.retry_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_RETRY_BACKOFF)
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<(), EmailClientError> {
+        let message = to_mime_message(&self.sender, recipient, message)?;
+
+        let mut attempt = 0;
+        loop {
+            match self.transport.send(message.clone()).await {
+                Ok(_) => return Ok(()),
+                // 5xx replies won't get better by trying again
+                Err(e) if e.is_permanent() || attempt >= self.max_retries => {
+                    return Err(EmailClientError::DeliveryFailed(e.to_string()));
+                }
+                Err(e) => {
+                    eprintln!("smtp delivery attempt {} failed: {e}", attempt + 1);
+                    tokio::time::sleep(self.retry_delay(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    // Just enough of an SMTP server to accept mail in-process
+    #[derive(Default, Clone)]
+    struct SmtpStub {
+        messages: Arc<Mutex<Vec<String>>>,
+        // replies to MAIL FROM, consumed one per transaction before falling back to 250
+        mail_replies: Arc<Mutex<VecDeque<&'static str>>>,
+        mail_commands: Arc<Mutex<u32>>,
+    }
+
+    impl SmtpStub {
+        async fn start(&self) -> u16 {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let stub = self.clone();
+
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(stub.clone().serve(stream));
+                }
+            });
+            port
+        }
+
+        async fn serve(self, stream: tokio::net::TcpStream) {
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+
+            write.write_all(b"220 stub ESMTP\r\n").await.unwrap();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let command = line.to_ascii_uppercase();
+                let reply = if command.starts_with("EHLO") || command.starts_with("HELO") {
+                    "250 stub"
+                } else if command.starts_with("MAIL") {
+                    *self.mail_commands.lock().unwrap() += 1;
+                    self.mail_replies
+                        .lock()
+                        .unwrap()
+                        .pop_front()
+                        .unwrap_or("250 OK")
+                } else if command.starts_with("DATA") {
+                    write.write_all(b"354 go ahead\r\n").await.unwrap();
+                    let mut data = String::new();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if line == "." {
+                            break;
+                        }
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    self.messages.lock().unwrap().push(data);
+                    "250 queued"
+                } else if command.starts_with("QUIT") {
+                    write.write_all(b"221 bye\r\n").await.unwrap();
+                    return;
+                } else {
+                    // RCPT, RSET, NOOP
+                    "250 OK"
+                };
+                write
+                    .write_all(format!("{reply}\r\n").as_bytes())
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+
+    fn client(port: u16) -> SmtpEmailClient {
+        let mut config = SmtpConfig::new("127.0.0.1", SmtpTls::None);
+        config.port = Some(port);
+        config.retry_backoff = Duration::from_millis(1);
+        SmtpEmailClient::new(config, "no-reply@example.com").unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Your login code".to_owned(),
+            text: "123456".to_owned(),
+            html: "<b>123456</b>".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_email_delivers_multipart_message() {
+        let stub = SmtpStub::default();
+        let client = client(stub.start().await);
+        let recipient = Email::parse("hnariman@gmail.com").unwrap();
+
+        client.send_email(&recipient, &message()).await.unwrap();
+
+        let messages = stub.messages.lock().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("To: hnariman@gmail.com"));
+        assert!(messages[0].contains("Subject: Your login code"));
+        assert!(messages[0].contains("multipart/alternative"));
+        assert!(messages[0].contains("<b>123456</b>"));
+    }
+
+    #[tokio::test]
+    async fn test_send_email_retries_transient_failures() {
+        let stub = SmtpStub::default();
+        stub.mail_replies
+            .lock()
+            .unwrap()
+            .extend(["451 try again later", "421 busy"]);
+        let client = client(stub.start().await);
+        let recipient = Email::parse("hnariman@gmail.com").unwrap();
+
+        client.send_email(&recipient, &message()).await.unwrap();
+
+        assert_eq!(*stub.mail_commands.lock().unwrap(), 3);
+        assert_eq!(stub.messages.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_email_gives_up_on_permanent_failure() {
+        let stub = SmtpStub::default();
+        stub.mail_replies
+            .lock()
+            .unwrap()
+            .push_back("550 mailbox unavailable");
+        let client = client(stub.start().await);
+        let recipient = Email::parse("hnariman@gmail.com").unwrap();
+
+        let result = client.send_email(&recipient, &message()).await;
+
+        assert!(matches!(result, Err(EmailClientError::DeliveryFailed(_))));
+        assert_eq!(*stub.mail_commands.lock().unwrap(), 1);
+        assert!(stub.messages.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_email_gives_up_after_max_retries() {
+        let stub = SmtpStub::default();
+        stub.mail_replies
+            .lock()
+            .unwrap()
+            .extend(["451 try again later"; 10]);
+        let client = client(stub.start().await);
+        let recipient = Email::parse("hnariman@gmail.com").unwrap();
+
+        let result = client.send_email(&recipient, &message()).await;
+
+        assert!(matches!(result, Err(EmailClientError::DeliveryFailed(_))));
+        assert_eq!(*stub.mail_commands.lock().unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_retry_delay_is_capped() {
+        let client = client(25);
+
+        assert_eq!(client.retry_delay(0), Duration::from_millis(1));
+        assert_eq!(client.retry_delay(3), Duration::from_millis(8));
+        assert_eq!(client.retry_delay(40), MAX_RETRY_BACKOFF);
+    }
+
+    #[test]
+    fn test_smtp_tls_from_str() {
+        assert_eq!("STARTTLS".parse(), Ok(SmtpTls::StartTls));
+        assert_eq!("tls".parse(), Ok(SmtpTls::Implicit));
+        assert_eq!("none".parse(), Ok(SmtpTls::None));
+        assert!(matches!(
+            "ssl3".parse::<SmtpTls>(),
+            Err(EmailClientError::InvalidConfig(_))
+        ));
+    }
+}
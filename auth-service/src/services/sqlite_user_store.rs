@@ -0,0 +1,315 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::str::FromStr;
+
+use sqlx::{
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions},
+    Row,
+};
+
+use crate::{
+    domain::{Email, HashedPassword, Password, PasswordHashError, User, UserStore, UserStoreError},
+    utils::constants::DUMMY_PASSWORD_HASH,
+};
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+#[derive(Debug, Clone)]
+pub struct SqliteUserStore {
+    pool: SqlitePool,
+}
+
+impl SqliteUserStore {
+    /// Open (or create) the database at `url` and bring its schema up to date.
+    /// # Errors
+    /// When the database can't be opened or a migration fails.
+    pub async fn connect(url: &str, max_connections: u32) -> Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await?;
+
+        Self::new(pool).await
+    }
+
+    /// # Errors
+    /// When a migration fails.
+    pub async fn new(pool: SqlitePool) -> Result<Self, sqlx::Error> {
+        MIGRATOR.run(&pool).await?;
+        Ok(Self { pool })
+    }
+}
+
+fn database_error(e: &sqlx::Error) -> UserStoreError {
+    match e {
+        sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
+        sqlx::Error::Database(db) if db.is_unique_violation() => UserStoreError::UserAlreadyExists,
+        e => UserStoreError::DatabaseError(e.to_string()),
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for SqliteUserStore {
+    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        sqlx::query("INSERT INTO users (email, password_hash, requires_2fa) VALUES (?, ?, ?)")
+            .bind(user.email.as_ref())
+            .bind(user.password.as_ref())
+            .bind(user.requires_2fa)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| database_error(&e))?;
+        Ok(())
+    }
+
+    async fn get_user(&self, email: &str) -> Result<User, UserStoreError> {
+        let email = Email::parse(email)?;
+
+        let row = sqlx::query("SELECT password_hash, requires_2fa FROM users WHERE email = ?")
+            .bind(email.as_ref())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| database_error(&e))?;
+
+        let password = HashedPassword::parse(row.get("password_hash"))
+            .map_err(UserStoreError::PasswordHash)?;
+        Ok(User::new(email, password, row.get("requires_2fa")))
+    }
+
+    async fn validate_user(&self, email: &str, password: &str) -> Result<(), UserStoreError> {
+        let password = Password::parse(password)?;
+        let user = match self.get_user(email).await {
+            Err(UserStoreError::UserNotFound) => {
+                // hash anyway, answering faster would tell the account doesn't exist
+                let _ = DUMMY_PASSWORD_HASH.verify(&password).await;
+                return Err(UserStoreError::UserNotFound);
+            }
+            result => result?,
+        };
+
+        user.password.verify(&password).await.map_err(|e| match e {
+            PasswordHashError::Mismatch => UserStoreError::InvalidCredentials,
+            e => UserStoreError::PasswordHash(e),
+        })
+    }
+
+    async fn update_password(
+        &mut self,
+        email: &str,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError> {
+        let email = Email::parse(email)?;
+
+        let result = sqlx::query("UPDATE users SET password_hash = ? WHERE email = ?")
+            .bind(password.as_ref())
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| database_error(&e))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::CreateUserError;
+
+    use super::*;
+
+    // a single connection that never closes, every connection to :memory: is a new database
+    async fn storage() -> SqliteUserStore {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        SqliteUserStore::new(pool).await.unwrap()
+    }
+
+    async fn user(email: &str, password: &str) -> User {
+        let params = argon2::Params::new(1024, 1, 1, None).unwrap();
+        let password = Password::parse(password).unwrap();
+        let hash = HashedPassword::hash(&password, params).await.unwrap();
+        User::new(Email::parse(email).unwrap(), hash, false)
+    }
+
+    #[tokio::test]
+    pub async fn test_add_user() {
+        let mut storage = storage().await;
+        let mock = user("hnariman@gmail.com", "123oi1u23").await;
+        let mock2 = user("h.nariman@gmail.com", "123oi1u23").await;
+        storage.add_user(mock).await.unwrap();
+        storage.add_user(mock2).await.unwrap();
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_add_user_existing_user() {
+        let mut storage = storage().await;
+        let mock = user("h.nariman@gmail.com", "123oi1u23").await;
+        let mock2 = user("h.nariman@gmail.com", "123oi1u23").await;
+        let _added_mock = storage.add_user(mock).await;
+        let expected = storage.add_user(mock2).await;
+
+        assert_eq!(expected, Err(UserStoreError::UserAlreadyExists));
+    }
+
+    #[tokio::test]
+    pub async fn test_add_user_short_password() {
+        let expected = Password::parse("123");
+        assert_eq!(expected, Err(CreateUserError::InvalidPassword));
+    }
+
+    #[tokio::test]
+    pub async fn test_add_user_invalid_email() {
+        let expected = Email::parse("h.narimangmail.com");
+        assert_eq!(expected, Err(CreateUserError::InvalidEmail));
+    }
+
+    #[tokio::test]
+    pub async fn test_get_user() {
+        let mut storage = storage().await;
+        let mut mock = user("tnariman@gmail.com", "123oi1u23").await;
+        mock.requires_2fa = true;
+        storage.add_user(mock.clone()).await.unwrap();
+
+        let found = storage.get_user(mock.email.as_ref()).await.unwrap();
+
+        assert_eq!(found, mock);
+    }
+
+    #[tokio::test]
+    pub async fn test_get_user_not_found() {
+        let storage = storage().await;
+
+        assert_eq!(
+            storage.get_user("tnariman@gmail.com").await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_user_password_is_not_stored_in_plaintext() {
+        let mut storage = storage().await;
+        let mock = user("tnariman@gmail.com", "123oi1u23").await;
+        storage.add_user(mock.clone()).await.unwrap();
+
+        let stored: String = sqlx::query_scalar("SELECT password_hash FROM users")
+            .fetch_one(&storage.pool)
+            .await
+            .unwrap();
+
+        assert!(stored.starts_with("$argon2id$"));
+    }
+
+    #[tokio::test]
+    pub async fn test_validate_user() {
+        let mut storage = storage().await;
+        let mock = user("hnariman@gmail.com", "123asdf987234").await;
+        storage.add_user(mock).await.unwrap();
+
+        let validation_result = storage
+            .validate_user("hnariman@gmail.com", "123asdf987234")
+            .await;
+
+        assert_eq!(validation_result, Ok(()));
+    }
+
+    #[tokio::test]
+    pub async fn test_validate_user_shall_throw_invalid_credentials() {
+        let mut storage = storage().await;
+        let mock = user("hnariman@gmail.com", "123asdf987234").await;
+        storage.add_user(mock).await.unwrap();
+
+        let validation_result = storage
+            .validate_user("hnariman@gmail.com", "123asdf98723")
+            .await;
+
+        assert_eq!(validation_result, Err(UserStoreError::InvalidCredentials));
+    }
+
+    #[tokio::test]
+    pub async fn test_validate_user_shall_throw_user_not_found_wrong_email() {
+        let email = "testing@gmail.com";
+        let pass = "123asldkfj123";
+        let mut storage = storage().await;
+        storage.add_user(user(email, pass).await).await.unwrap();
+
+        let validation_result = storage.validate_user("testingssss@gmail.com", pass).await;
+
+        assert_eq!(validation_result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    pub async fn test_update_password() {
+        let mut storage = storage().await;
+        let mock = user("hnariman@gmail.com", "123asdf987234").await;
+        storage.add_user(mock).await.unwrap();
+
+        let legacy = bcrypt::hash("987asdf123432", 4).unwrap();
+        storage
+            .update_password(
+                "hnariman@gmail.com",
+                HashedPassword::parse(&legacy).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage
+                .validate_user("hnariman@gmail.com", "987asdf123432")
+                .await,
+            Ok(())
+        );
+        assert_eq!(
+            storage
+                .validate_user("hnariman@gmail.com", "123asdf987234")
+                .await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_update_password_unknown_user() {
+        let mut storage = storage().await;
+        let hash = user("hnariman@gmail.com", "123asdf987234").await.password;
+
+        assert_eq!(
+            storage.update_password("hnariman@gmail.com", hash).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_users_survive_reconnect() {
+        let path = std::env::temp_dir().join(format!("{}.db", uuid::Uuid::new_v4()));
+        let url = format!("sqlite:{}", path.display());
+        let mock = user("hnariman@gmail.com", "123asdf987234").await;
+
+        let mut storage = SqliteUserStore::connect(&url, 2).await.unwrap();
+        storage.add_user(mock.clone()).await.unwrap();
+        storage.pool.close().await;
+
+        // migrations are idempotent, the second start finds the same user
+        let storage = SqliteUserStore::connect(&url, 2).await.unwrap();
+        assert_eq!(storage.get_user(mock.email.as_ref()).await, Ok(mock));
+
+        storage.pool.close().await;
+        let _ = std::fs::remove_file(path);
+    }
+}
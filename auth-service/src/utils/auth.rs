@@ -1,18 +1,41 @@
-use axum_extra::extract::cookie::{Cookie, SameSite};
-use chrono::Utc;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, decode_header, errors::ErrorKind, Validation};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    domain::{BannedTokenStore, Email},
-    services::HashsetBannedTokenStore,
+    app_state::AppState,
+    domain::{AuthAPIError, Authentication, BannedTokenStore, Email, RefreshToken},
 };
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::{
+    constants::{
+        JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_ISSUER, OAUTH_AUDIENCE, REFRESH_TOKEN_COOKIE_NAME,
+        REFRESH_TOKEN_TTL_SECONDS,
+    },
+    keyring::Keyring,
+};
 
-// Create cookie with a new JWT auth token
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>, GenerateTokenError> {
-    let token = generate_auth_token(email)?;
+// Create cookie with a new JWT auth token, it records how the user logged in
+pub fn generate_auth_cookie(
+    email: &Email,
+    authentication: &Authentication,
+    issued_at: DateTime<Utc>,
+    keyring: &Keyring,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let claims = Claims {
+        auth_time: Some(authentication.auth_time),
+        amr: authentication.amr.clone(),
+        ..new_claims(email.as_ref(), issued_at)?
+    };
+    let token = create_token(&claims, keyring)?;
     Ok(create_auth_cookie(token))
 }
 
@@ -27,6 +50,16 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
+// Cookie carrying the opaque refresh token, only ever read by the server
+pub fn create_refresh_cookie(token: &RefreshToken) -> Cookie<'static> {
+    Cookie::build((REFRESH_TOKEN_COOKIE_NAME, token.as_ref().to_owned()))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict) // never sent along with cross-site requests
+        .max_age(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        .build()
+}
+
 #[derive(Debug)]
 pub enum GenerateTokenError {
     TokenError(jsonwebtoken::errors::Error),
@@ -37,12 +70,50 @@ pub enum GenerateTokenError {
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
 
 // Create JWT auth token
-fn generate_auth_token(email: &Email) -> Result<String, GenerateTokenError> {
+pub fn generate_auth_token(email: &Email, keyring: &Keyring) -> Result<String, GenerateTokenError> {
+    create_token(&new_claims(email.as_ref(), Utc::now())?, keyring)
+}
+
+// When a new session of `sub` starts: revoking tokens takes everything issued up to the current
+// second, so a session started within that same second is dated the next one to survive it
+pub async fn session_issued_at(
+    sub: &str,
+    banned: &dyn BannedTokenStore,
+) -> Result<DateTime<Utc>, GenerateTokenError> {
+    let now = Utc::now();
+    let iat = usize::try_from(now.timestamp()).map_err(|_| GenerateTokenError::UnexpectedError)?;
+    if banned.check_issued(sub, iat).await.is_ok() {
+        return Ok(now);
+    }
+
+    let delta = chrono::Duration::try_seconds(1).ok_or(GenerateTokenError::UnexpectedError)?;
+    now.checked_add_signed(delta)
+        .ok_or(GenerateTokenError::UnexpectedError)
+}
+
+// Create OAuth access token issued to `client_id`, on behalf of a user or of the client itself
+pub fn generate_access_token(
+    sub: &str,
+    client_id: &str,
+    scope: Option<&str>,
+    keyring: &Keyring,
+) -> Result<String, GenerateTokenError> {
+    let claims = Claims {
+        aud: OAUTH_AUDIENCE.to_owned(),
+        client_id: Some(client_id.to_owned()),
+        scope: scope.map(str::to_owned),
+        ..new_claims(sub, Utc::now())?
+    };
+    create_token(&claims, keyring)
+}
+
+// Claims of a token for `sub` issued at `now`
+fn new_claims(sub: &str, now: DateTime<Utc>) -> Result<Claims, GenerateTokenError> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .ok_or(GenerateTokenError::UnexpectedError)?;
 
     // Create JWT expiration time
-    let exp = Utc::now()
+    let exp = now
         .checked_add_signed(delta)
         .ok_or(GenerateTokenError::UnexpectedError)?
         .timestamp();
@@ -52,61 +123,210 @@ fn generate_auth_token(email: &Email) -> Result<String, GenerateTokenError> {
         .try_into()
         .map_err(|_| GenerateTokenError::UnexpectedError)?;
 
-    let sub = email.as_ref().to_owned();
-
-    let claims = Claims { sub, exp };
+    let iat: usize = now
+        .timestamp()
+        .try_into()
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
 
-    create_token(&claims).map_err(GenerateTokenError::TokenError)
+    Ok(Claims {
+        sub: sub.to_owned(),
+        exp,
+        iat,
+        jti: uuid::Uuid::new_v4().to_string(),
+        iss: JWT_ISSUER.to_owned(),
+        aud: JWT_AUDIENCE.to_owned(),
+        auth_time: None,
+        amr: Vec::new(),
+        client_id: None,
+        scope: None,
+    })
 }
 
-// Check if JWT auth token is valid by decoding it using the JWT secret
+// Check if JWT auth token is valid by verifying its signature with the key named in its header
 pub async fn validate_token(
     token: &str,
-    banned: HashsetBannedTokenStore,
+    keyring: &Keyring,
+    banned: &dyn BannedTokenStore,
 ) -> Result<Claims, jsonwebtoken::errors::Error> {
-    if banned.check(token.to_string()).await.is_err() {
-        return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    let kid = decode_header(token)?.kid;
+    let key = keyring
+        .verifying_key(kid.as_deref(), Utc::now())
+        .ok_or(ErrorKind::InvalidToken)?;
+
+    let mut validation = Validation::new(key.algorithm);
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    // login sessions and OAuth access tokens alike, callers tell them apart with `Claims::is_session`
+    validation.set_audience(&[JWT_AUDIENCE.as_str(), OAUTH_AUDIENCE.as_str()]);
+
+    let claims =
+        decode::<Claims>(token, key.decoding_key(), &validation).map(|data| data.claims)?;
+
+    if banned.check(claims.jti.clone()).await.is_err() {
+        return Err(ErrorKind::InvalidToken.into());
     }
 
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
+    // tokens issued before e.g. a password change are revoked as a whole
+    if banned.check_issued(&claims.sub, claims.iat).await.is_err() {
+        return Err(ErrorKind::InvalidToken.into());
+    }
+
+    Ok(claims)
 }
 
-// Create JWT auth token by encoding claims using the JWT secret
-fn create_token(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
-    )
+// Create JWT by signing claims with the active key of the keyring
+pub(crate) fn create_token(
+    claims: &impl Serialize,
+    keyring: &Keyring,
+) -> Result<String, GenerateTokenError> {
+    keyring
+        .active_key(Utc::now())
+        .ok_or(GenerateTokenError::UnexpectedError)?
+        .sign(claims)
+        .map_err(GenerateTokenError::TokenError)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    pub iat: usize,
+    // unique per token, a single token is revoked by banning its jti
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    // set on the tokens of a login session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    // set on OAuth access tokens, `sub` is the client itself for the client credentials grant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+impl Claims {
+    // A token of the user's own login, rather than one issued to an OAuth client
+    pub fn is_session(&self) -> bool {
+        self.client_id.is_none() && self.aud == *JWT_AUDIENCE
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope
+            .as_deref()
+            .is_some_and(|scopes| scopes.split_whitespace().any(|each| each == scope))
+    }
+}
+
+// Claims of the caller's login session, sent as `Authorization: Bearer <jwt>` by API clients
+// or as the jwt cookie by the browser. OAuth access tokens are rejected, they only grant their scopes.
+#[async_trait::async_trait]
+impl FromRequestParts<AppState> for Claims {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = match bearer_token(&parts.headers)? {
+            Some(token) => token.to_owned(),
+            None => CookieJar::from_headers(&parts.headers)
+                .get(JWT_COOKIE_NAME)
+                .ok_or(AuthAPIError::MissingToken)?
+                .value()
+                .to_owned(),
+        };
+
+        let claims = request_claims(&token, state).await?;
+        if !claims.is_session() {
+            return Err(AuthAPIError::InvalidToken);
+        }
+        Ok(claims)
+    }
+}
+
+// Claims of an OAuth access token from `/token`, only ever sent as `Authorization: Bearer`
+#[derive(Debug)]
+pub struct AccessTokenClaims(pub Claims);
+
+#[async_trait::async_trait]
+impl FromRequestParts<AppState> for AccessTokenClaims {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = bearer_token(&parts.headers)?.ok_or(AuthAPIError::MissingToken)?;
+
+        let claims = request_claims(token, state).await?;
+        if claims.is_session() {
+            return Err(AuthAPIError::InvalidToken);
+        }
+        Ok(Self(claims))
+    }
+}
+
+async fn request_claims(token: &str, state: &AppState) -> Result<Claims, AuthAPIError> {
+    validate_token(
+        token,
+        &*state.keyring.read().await,
+        &*state.banned_tokens.read().await,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)
+}
+
+// Token of an `Authorization: Bearer` header, any other scheme is rejected rather than ignored
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Result<Option<&str>, AuthAPIError> {
+    let Some(value) = headers.get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+
+    let (scheme, token) = value
+        .to_str()
+        .ok()
+        .and_then(|value| value.split_once(' '))
+        .ok_or(AuthAPIError::InvalidToken)?;
+
+    if !scheme.eq_ignore_ascii_case("bearer") || token.trim().is_empty() {
+        return Err(AuthAPIError::InvalidToken);
+    }
+    Ok(Some(token.trim()))
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::services::HashsetBannedTokenStore;
+    use crate::{services::HashsetBannedTokenStore, utils::signing_key::SigningKey};
 
     use super::*;
 
+    fn keyring() -> Keyring {
+        Keyring::single(SigningKey::hmac(b"secret"))
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email = Email::parse("test@example.com").unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let authentication = Authentication::now(true);
+        let issued_at = Utc::now();
+        let cookie = generate_auth_cookie(&email, &authentication, issued_at, &keyring()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+
+        let banned = HashsetBannedTokenStore::default();
+        let claims = validate_token(cookie.value(), &keyring(), &banned)
+            .await
+            .unwrap();
+        // both captured above, a second boundary in between changes nothing
+        assert_eq!(claims.iat, usize::try_from(issued_at.timestamp()).unwrap());
+        assert_eq!(claims.auth_time, Some(authentication.auth_time));
+        assert_eq!(claims.amr, vec!["pwd", "otp", "mfa"]);
     }
 
     #[tokio::test]
@@ -120,19 +340,33 @@ mod tests {
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_create_refresh_cookie() {
+        let token = RefreshToken::default();
+        let cookie = create_refresh_cookie(&token);
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.value(), token.as_ref());
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        );
+    }
+
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse("test@example.com").unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&email, &keyring()).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let email = Email::parse("test@example.com").unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&email, &keyring()).unwrap();
         let banned = HashsetBannedTokenStore::default();
-        let result = validate_token(&token, banned).await.unwrap();
+        let result = validate_token(&token, &keyring(), &banned).await.unwrap();
         assert_eq!(result.sub, "test@example.com");
 
         let exp = Utc::now()
@@ -143,11 +377,159 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_validate_token_issued_before_revocation() {
+        let email = Email::parse("test@example.com").unwrap();
+        let token = generate_auth_token(&email, &keyring()).unwrap();
+        let mut banned = HashsetBannedTokenStore::default();
+        let issued_before = usize::try_from(Utc::now().timestamp()).unwrap() + 1;
+
+        banned
+            .revoke_issued_before(email.as_ref().to_owned(), issued_before)
+            .await
+            .unwrap();
+
+        assert!(validate_token(&token, &keyring(), &banned).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_session_issued_after_revocation() {
+        let email = Email::parse("test@example.com").unwrap();
+        let mut banned = HashsetBannedTokenStore::default();
+        assert!(session_issued_at(email.as_ref(), &banned).await.unwrap() <= Utc::now());
+
+        let issued_before = usize::try_from(Utc::now().timestamp()).unwrap() + 1;
+        banned
+            .revoke_issued_before(email.as_ref().to_owned(), issued_before)
+            .await
+            .unwrap();
+
+        let issued_at = session_issued_at(email.as_ref(), &banned).await.unwrap();
+        let cookie =
+            generate_auth_cookie(&email, &Authentication::now(false), issued_at, &keyring())
+                .unwrap();
+        assert!(validate_token(cookie.value(), &keyring(), &banned)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_has_unique_jti() {
+        let email = Email::parse("test@example.com").unwrap();
+        let banned = HashsetBannedTokenStore::default();
+        let first = validate_token(
+            &generate_auth_token(&email, &keyring()).unwrap(),
+            &keyring(),
+            &banned,
+        )
+        .await
+        .unwrap();
+        let second = validate_token(
+            &generate_auth_token(&email, &keyring()).unwrap(),
+            &keyring(),
+            &banned,
+        )
+        .await
+        .unwrap();
+
+        assert!(uuid::Uuid::parse_str(&first.jti).is_ok());
+        assert_ne!(first.jti, second.jti);
+        assert_eq!(first.iss, *JWT_ISSUER);
+        assert_eq!(first.aud, *JWT_AUDIENCE);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_banned_jti() {
+        let email = Email::parse("test@example.com").unwrap();
+        let token = generate_auth_token(&email, &keyring()).unwrap();
+        let other = generate_auth_token(&email, &keyring()).unwrap();
+        let mut banned = HashsetBannedTokenStore::default();
+        let claims = validate_token(&token, &keyring(), &banned).await.unwrap();
+
+        banned.add(claims.jti, claims.exp).await.unwrap();
+
+        assert!(validate_token(&token, &keyring(), &banned).await.is_err());
+        // only that token is revoked
+        assert!(validate_token(&other, &keyring(), &banned).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_foreign_audience() {
+        let now = usize::try_from(Utc::now().timestamp()).unwrap();
+        let claims = Claims {
+            sub: "test@example.com".to_owned(),
+            exp: now + 600,
+            iat: now,
+            jti: uuid::Uuid::new_v4().to_string(),
+            iss: JWT_ISSUER.to_owned(),
+            aud: "another-service".to_owned(),
+            auth_time: None,
+            amr: Vec::new(),
+            client_id: None,
+            scope: None,
+        };
+        let token = create_token(&claims, &keyring()).unwrap();
+        let banned = HashsetBannedTokenStore::default();
+
+        assert!(validate_token(&token, &keyring(), &banned).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_generate_access_token_for_client() {
+        let token =
+            generate_access_token("app-service", "app-service", Some("introspect"), &keyring())
+                .unwrap();
+        let banned = HashsetBannedTokenStore::default();
+
+        let claims = validate_token(&token, &keyring(), &banned).await.unwrap();
+        assert_eq!(claims.sub, "app-service");
+        assert_eq!(claims.aud, *OAUTH_AUDIENCE);
+        assert!(!claims.is_session());
+        assert_eq!(claims.client_id.as_deref(), Some("app-service"));
+        assert!(claims.has_scope("introspect"));
+        assert!(!claims.has_scope("intro"));
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
         let banned = HashsetBannedTokenStore::default();
-        let result = validate_token(&token, banned).await;
+        let result = validate_token(&token, &keyring(), &banned).await;
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_bearer_token() {
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, value.parse().unwrap());
+            headers
+        };
+
+        assert!(matches!(bearer_token(&HeaderMap::new()), Ok(None)));
+        assert!(matches!(
+            bearer_token(&headers("Bearer abc.def.ghi")),
+            Ok(Some("abc.def.ghi"))
+        ));
+        assert!(matches!(
+            bearer_token(&headers("bearer abc.def.ghi")),
+            Ok(Some("abc.def.ghi"))
+        ));
+        for invalid in ["Basic dXNlcjpwYXNz", "Bearer", "Bearer  ", "abc.def.ghi"] {
+            assert!(matches!(
+                bearer_token(&headers(invalid)),
+                Err(AuthAPIError::InvalidToken)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_signed_with_another_key() {
+        let email = Email::parse("test@example.com").unwrap();
+        let token =
+            generate_auth_token(&email, &Keyring::single(SigningKey::hmac(b"other"))).unwrap();
+        let banned = HashsetBannedTokenStore::default();
+
+        assert!(validate_token(&token, &keyring(), &banned).await.is_err());
+    }
 }
@@ -1,4 +1,35 @@
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+
+// How long a 2FA code sent by email stays valid
+pub const TWO_FA_CODE_TTL_SECONDS: i64 = 600; // 10 minutes
+
+// Wrong guesses allowed before a pending login attempt is dropped
+pub const TWO_FA_MAX_ATTEMPTS: u32 = 3;
+
+// How long a password reset link stays valid
+pub const PASSWORD_RESET_TOKEN_TTL_SECONDS: i64 = 900; // 15 minutes
+
+// How long a refresh token can be used, every refresh starts this over
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 1_209_600; // 14 days
+
+// How long a login lasts at most, however often it is refreshed
+pub const REFRESH_TOKEN_FAMILY_TTL_SECONDS: i64 = 7_776_000; // 90 days
+
+// How often expired refresh tokens are evicted
+pub const REFRESH_TOKEN_PRUNE_INTERVAL_SECONDS: u64 = 3_600;
+
+// How long an OAuth authorization code can be redeemed at `/token`
+pub const AUTHORIZATION_CODE_TTL_SECONDS: i64 = 60;
+
+// How long a device has to wait for its user, and how often it may poll `/token` meanwhile
+pub const DEVICE_CODE_TTL_SECONDS: i64 = 600; // 10 minutes
+pub const DEVICE_CODE_POLL_INTERVAL_SECONDS: i64 = 5;
+// RFC 8628 grant type of a device polling `/token`
+pub const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+// Pool size of the database stores when `DATABASE_MAX_CONNECTIONS` isn't set
+pub const DEFAULT_DATABASE_MAX_CONNECTIONS: u32 = 5;
 
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
@@ -8,27 +39,176 @@ pub mod test {
     pub const APP_ADDRESS: &str = "127.0.0.1:0";
 }
 
-// This is definitely NOT a good secret. We will update it soon!
-// pub const JWT_SECRET: &str = "secret";
-
+use crate::domain::{BreachedPasswords, HashedPassword, PasswordPolicy, PASSWORD_MIN_LENGTH_FLOOR};
 use dotenvy::dotenv;
 use lazy_static::lazy_static;
 use std::env as std_env;
 
 // Define a lazily evaluated static. lazy_static is needed because std_env::var is not a const function.
 lazy_static! {
-    pub static ref JWT_SECRET: String = set_token();
+    pub static ref EMAIL_SENDER: String =
+        env_or_default(env::EMAIL_SENDER_ENV_VAR, "no-reply@auth-service.local");
+    pub static ref EMAIL_OUTBOX_DIR: String =
+        env_or_default(env::EMAIL_OUTBOX_DIR_ENV_VAR, "outbox");
+    // Public URL of the UI, used for links in emails
+    pub static ref APP_URL: String = env_or_default(env::APP_URL_ENV_VAR, "http://localhost:3000");
+    // `iss` and `aud` claims of the JWTs we issue, checked when validating them
+    pub static ref JWT_ISSUER: String = env_or_default(env::JWT_ISSUER_ENV_VAR, &APP_URL);
+    pub static ref JWT_AUDIENCE: String = env_or_default(env::JWT_AUDIENCE_ENV_VAR, "auth-service");
+    // `aud` of the OAuth access tokens from `/token`, so they are never taken for a login session
+    pub static ref OAUTH_AUDIENCE: String =
+        env_or_default(env::OAUTH_AUDIENCE_ENV_VAR, "auth-service-oauth");
+    pub static ref DATABASE_URL: String =
+        env_or_default(env::DATABASE_URL_ENV_VAR, "sqlite:auth.db");
+    pub static ref DATABASE_MAX_CONNECTIONS: u32 =
+        env_or_default(
+            env::DATABASE_MAX_CONNECTIONS_ENV_VAR,
+            &DEFAULT_DATABASE_MAX_CONNECTIONS.to_string(),
+        )
+            .parse()
+            .expect("DATABASE_MAX_CONNECTIONS must be a number.");
+    // Banned tokens go to this RESP server when set
+    pub static ref REDIS_URL: Option<String> = env_optional(env::REDIS_URL_ENV_VAR);
+    // How often the in-memory banned token store evicts expired tokens
+    pub static ref BANNED_TOKEN_PRUNE_INTERVAL_SECONDS: u64 =
+        env_or_default(env::BANNED_TOKEN_PRUNE_INTERVAL_ENV_VAR, "60")
+            .parse()
+            .ok()
+            .filter(|&seconds| seconds > 0)
+            .expect("BANNED_TOKEN_PRUNE_INTERVAL_SECONDS must be a positive number.");
+    // JSON file of the clients allowed to use the OAuth endpoints
+    pub static ref OAUTH_CLIENTS_PATH: Option<String> = env_optional(env::OAUTH_CLIENTS_PATH_ENV_VAR);
+    pub static ref ARGON2_PARAMS: argon2::Params = set_argon2_params();
+    // Checked against when a login names an unknown account, so it costs as much as a known one
+    pub static ref DUMMY_PASSWORD_HASH: HashedPassword = set_dummy_password_hash();
+    // Rules for new passwords, the breached passwords list is loaded along with it
+    pub static ref PASSWORD_POLICY: PasswordPolicy = set_password_policy();
+}
+
+// Argon2id cost, defaults follow the OWASP recommendation (19 MiB, 2 iterations, 1 lane)
+fn set_argon2_params() -> argon2::Params {
+    let cost = |name: &str, default: u32| {
+        env_or_default(name, &default.to_string())
+            .parse::<u32>()
+            .unwrap_or_else(|_| panic!("{name} must be a number."))
+    };
+
+    argon2::Params::new(
+        cost(
+            env::ARGON2_MEMORY_KIB_ENV_VAR,
+            argon2::Params::DEFAULT_M_COST,
+        ),
+        cost(
+            env::ARGON2_ITERATIONS_ENV_VAR,
+            argon2::Params::DEFAULT_T_COST,
+        ),
+        cost(
+            env::ARGON2_PARALLELISM_ENV_VAR,
+            argon2::Params::DEFAULT_P_COST,
+        ),
+        None,
+    )
+    .expect("Invalid Argon2 parameters.")
 }
 
-fn set_token() -> String {
-    dotenv().ok(); // Load environment variables
-    let secret = std_env::var(env::JWT_SECRET_ENV_VAR).expect("JWT_SECRET must be set.");
-    if secret.is_empty() {
-        panic!("JWT_SECRET must not be empty.");
+fn set_dummy_password_hash() -> HashedPassword {
+    use argon2::{
+        password_hash::{rand_core::OsRng, SaltString},
+        Algorithm, Argon2, PasswordHasher, Version,
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, ARGON2_PARAMS.clone())
+        .hash_password(b"no account has this password", &salt)
+        .expect("Unable to hash the dummy password.");
+    HashedPassword::parse(&hash.to_string()).expect("Invalid dummy password hash.")
+}
+
+// Panics on invalid values or an unreadable blocklist, the service shouldn't start half configured
+fn set_password_policy() -> PasswordPolicy {
+    let mut policy = PasswordPolicy::default();
+
+    if let Some(min_length) = env_optional(env::PASSWORD_MIN_LENGTH_ENV_VAR) {
+        policy.min_length = min_length
+            .parse()
+            .ok()
+            .filter(|min_length| *min_length >= PASSWORD_MIN_LENGTH_FLOOR)
+            .unwrap_or_else(|| {
+                panic!(
+                    "PASSWORD_MIN_LENGTH must be a number of at least {PASSWORD_MIN_LENGTH_FLOOR}."
+                )
+            });
+    }
+    if let Some(max_length) = env_optional(env::PASSWORD_MAX_LENGTH_ENV_VAR) {
+        policy.max_length = max_length
+            .parse()
+            .ok()
+            .filter(|max_length| *max_length >= policy.min_length)
+            .expect("PASSWORD_MAX_LENGTH must be a number not below the minimal length.");
+    }
+    if let Some(min_score) = env_optional(env::PASSWORD_MIN_SCORE_ENV_VAR) {
+        policy.min_score = min_score
+            .parse::<u8>()
+            .ok()
+            .and_then(|score| zxcvbn::Score::try_from(score).ok())
+            .expect("PASSWORD_MIN_SCORE must be a number between 0 and 4.");
     }
-    secret
+    if let Some(words) = env_optional(env::PASSWORD_CONTEXT_WORDS_ENV_VAR) {
+        policy.context_words = words
+            .split(',')
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+    }
+    if let Some(path) = env_optional(env::PASSWORD_BLOCKLIST_PATH_ENV_VAR) {
+        let breached = BreachedPasswords::load(&path)
+            .unwrap_or_else(|e| panic!("Unable to load password blocklist {path}: {e}"));
+        policy.breached = Some(std::sync::Arc::new(breached));
+    }
+    policy
+}
+
+fn env_or_default(name: &str, default: &str) -> String {
+    env_optional(name).unwrap_or_else(|| default.to_owned())
+}
+
+pub(crate) fn env_optional(name: &str) -> Option<String> {
+    dotenv().ok();
+    std_env::var(name).ok().filter(|value| !value.is_empty())
 }
 
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_KEYRING_DIR_ENV_VAR: &str = "JWT_KEYRING_DIR";
+    pub const JWT_SIGNING_KEY_PATH_ENV_VAR: &str = "JWT_SIGNING_KEY_PATH";
+    pub const JWT_KEY_ID_ENV_VAR: &str = "JWT_KEY_ID";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCE_ENV_VAR: &str = "JWT_AUDIENCE";
+    pub const OAUTH_AUDIENCE_ENV_VAR: &str = "OAUTH_AUDIENCE";
+    pub const EMAIL_SENDER_ENV_VAR: &str = "EMAIL_SENDER";
+    pub const EMAIL_OUTBOX_DIR_ENV_VAR: &str = "EMAIL_OUTBOX_DIR";
+    pub const APP_URL_ENV_VAR: &str = "APP_URL";
+    pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
+    pub const DATABASE_MAX_CONNECTIONS_ENV_VAR: &str = "DATABASE_MAX_CONNECTIONS";
+    pub const DATABASE_MIN_CONNECTIONS_ENV_VAR: &str = "DATABASE_MIN_CONNECTIONS";
+    pub const REDIS_URL_ENV_VAR: &str = "REDIS_URL";
+    pub const BANNED_TOKEN_PRUNE_INTERVAL_ENV_VAR: &str = "BANNED_TOKEN_PRUNE_INTERVAL_SECONDS";
+    pub const DATABASE_ACQUIRE_TIMEOUT_ENV_VAR: &str = "DATABASE_ACQUIRE_TIMEOUT_SECONDS";
+    pub const ARGON2_MEMORY_KIB_ENV_VAR: &str = "ARGON2_MEMORY_KIB";
+    pub const ARGON2_ITERATIONS_ENV_VAR: &str = "ARGON2_ITERATIONS";
+    pub const ARGON2_PARALLELISM_ENV_VAR: &str = "ARGON2_PARALLELISM";
+    pub const PASSWORD_MIN_LENGTH_ENV_VAR: &str = "PASSWORD_MIN_LENGTH";
+    pub const PASSWORD_MAX_LENGTH_ENV_VAR: &str = "PASSWORD_MAX_LENGTH";
+    pub const PASSWORD_MIN_SCORE_ENV_VAR: &str = "PASSWORD_MIN_SCORE";
+    pub const PASSWORD_CONTEXT_WORDS_ENV_VAR: &str = "PASSWORD_CONTEXT_WORDS";
+    pub const PASSWORD_BLOCKLIST_PATH_ENV_VAR: &str = "PASSWORD_BLOCKLIST_PATH";
+    pub const SMTP_HOST_ENV_VAR: &str = "SMTP_HOST";
+    pub const SMTP_PORT_ENV_VAR: &str = "SMTP_PORT";
+    pub const SMTP_USERNAME_ENV_VAR: &str = "SMTP_USERNAME";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    pub const SMTP_TLS_ENV_VAR: &str = "SMTP_TLS";
+    pub const SMTP_POOL_SIZE_ENV_VAR: &str = "SMTP_POOL_SIZE";
+    pub const SMTP_MAX_RETRIES_ENV_VAR: &str = "SMTP_MAX_RETRIES";
+    pub const OAUTH_CLIENTS_PATH_ENV_VAR: &str = "OAUTH_CLIENTS_PATH";
 }
@@ -0,0 +1,138 @@
+use askama::Template;
+use lettre::{message::MultiPart, Message};
+
+use crate::domain::{Email, EmailClientError, EmailMessage, TwoFACode};
+
+#[derive(Template)]
+#[template(path = "email/two_fa_code.html")]
+struct TwoFACodeHtml<'a> {
+    code: &'a str,
+    ttl_minutes: i64,
+}
+
+#[derive(Template)]
+#[template(path = "email/two_fa_code.txt")]
+struct TwoFACodeText<'a> {
+    code: &'a str,
+    ttl_minutes: i64,
+}
+
+// Email carrying the code for the second login step
+pub fn two_fa_code_email(
+    code: &TwoFACode,
+    ttl_minutes: i64,
+) -> Result<EmailMessage, askama::Error> {
+    let code = code.as_ref();
+
+    Ok(EmailMessage {
+        subject: "Your login code".to_owned(),
+        text: TwoFACodeText { code, ttl_minutes }.render()?,
+        html: TwoFACodeHtml { code, ttl_minutes }.render()?,
+    })
+}
+
+#[derive(Template)]
+#[template(path = "email/password_reset.html")]
+struct PasswordResetHtml<'a> {
+    link: &'a str,
+    ttl_minutes: i64,
+}
+
+#[derive(Template)]
+#[template(path = "email/password_reset.txt")]
+struct PasswordResetText<'a> {
+    link: &'a str,
+    ttl_minutes: i64,
+}
+
+// Email carrying the single-use password reset link
+pub fn password_reset_email(link: &str, ttl_minutes: i64) -> Result<EmailMessage, askama::Error> {
+    Ok(EmailMessage {
+        subject: "Reset your password".to_owned(),
+        text: PasswordResetText { link, ttl_minutes }.render()?,
+        html: PasswordResetHtml { link, ttl_minutes }.render()?,
+    })
+}
+
+// Build a multipart/alternative MIME message, shared by every client that talks RFC 5322
+pub fn to_mime_message(
+    sender: &str,
+    recipient: &Email,
+    message: &EmailMessage,
+) -> Result<Message, EmailClientError> {
+    let invalid = |e: &dyn std::fmt::Display| EmailClientError::InvalidMessage(e.to_string());
+
+    Message::builder()
+        .from(sender.parse().map_err(|e| invalid(&e))?)
+        .to(recipient.as_ref().parse().map_err(|e| invalid(&e))?)
+        .subject(&message.subject)
+        .multipart(MultiPart::alternative_plain_html(
+            message.text.clone(),
+            message.html.clone(),
+        ))
+        .map_err(|e| invalid(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_fa_code_email_renders_both_parts() {
+        let code = TwoFACode::parse("123456").unwrap();
+        let message = two_fa_code_email(&code, 10).unwrap();
+
+        assert_eq!(message.subject, "Your login code");
+        assert!(message.text.contains("123456"));
+        assert!(message.text.contains("10 minutes"));
+        assert!(message.html.contains("123456"));
+        assert!(message.html.starts_with("<!DOCTYPE html>"));
+    }
+
+    #[test]
+    fn test_password_reset_email_contains_link() {
+        let link = "http://localhost:3000/?reset_token=abc123";
+        let message = password_reset_email(link, 15).unwrap();
+
+        assert_eq!(message.subject, "Reset your password");
+        assert!(message.text.contains(link));
+        assert!(message.text.contains("15 minutes"));
+        assert!(message.html.contains(&format!("href=\"{link}\"")));
+    }
+
+    #[test]
+    fn test_to_mime_message_rejects_invalid_sender() {
+        let recipient = Email::parse("hnariman@gmail.com").unwrap();
+        let message = EmailMessage {
+            subject: "subject".to_owned(),
+            text: "text".to_owned(),
+            html: "<p>html</p>".to_owned(),
+        };
+
+        assert!(matches!(
+            to_mime_message("not an address", &recipient, &message),
+            Err(EmailClientError::InvalidMessage(_))
+        ));
+    }
+
+    #[test]
+    fn test_to_mime_message_is_multipart_alternative() {
+        let recipient = Email::parse("hnariman@gmail.com").unwrap();
+        let message = EmailMessage {
+            subject: "subject".to_owned(),
+            text: "text".to_owned(),
+            html: "<p>html</p>".to_owned(),
+        };
+
+        let formatted = String::from_utf8(
+            to_mime_message("no-reply@example.com", &recipient, &message)
+                .unwrap()
+                .formatted(),
+        )
+        .unwrap();
+
+        assert!(formatted.contains("Content-Type: multipart/alternative"));
+        assert!(formatted.contains("Content-Type: text/plain"));
+        assert!(formatted.contains("Content-Type: text/html"));
+    }
+}
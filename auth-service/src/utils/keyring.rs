@@ -0,0 +1,346 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{jwk::JwkSet, Algorithm};
+use serde::Deserialize;
+
+use super::{
+    auth::TOKEN_TTL_SECONDS,
+    constants::{env, env_optional},
+    signing_key::{SigningKey, SigningKeyError},
+};
+
+// Manifest listing the keys of a keyring directory
+pub const KEYRING_MANIFEST: &str = "keyring.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeyringError {
+    #[error("Unable to read keyring manifest: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid keyring manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("Invalid timestamp for key {0}, expected RFC 3339")]
+    InvalidTimestamp(String),
+    #[error("Unable to load key {0}: {1}")]
+    Key(String, SigningKeyError),
+    #[error("Duplicate key id {0}")]
+    DuplicateKid(String),
+    #[error("No key is active right now")]
+    NoActiveKey,
+    #[error("JWT_SECRET must be set when no signing key is configured")]
+    MissingSecret,
+}
+
+#[derive(Clone)]
+pub struct KeyringEntry {
+    pub key: SigningKey,
+    pub activates_at: DateTime<Utc>,
+    // signs nothing from then on, but verifies until its last tokens have expired
+    pub retires_at: Option<DateTime<Utc>>,
+}
+
+impl KeyringEntry {
+    #[must_use]
+    pub fn new(key: SigningKey) -> Self {
+        Self {
+            key,
+            activates_at: DateTime::UNIX_EPOCH,
+            retires_at: None,
+        }
+    }
+
+    fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.activates_at <= now && self.retires_at.is_none_or(|retires_at| now < retires_at)
+    }
+
+    // Upcoming keys verify as well, replicas may not agree to the second on when a key activates
+    fn can_verify(&self, now: DateTime<Utc>) -> bool {
+        self.retires_at
+            .is_none_or(|retires_at| now < retires_at + Duration::seconds(TOKEN_TTL_SECONDS))
+    }
+}
+
+// Signing keys with overlapping lifetimes, so keys rotate without logging anyone out:
+// publish the next key ahead of its activation, then retire the old one.
+#[derive(Clone)]
+pub struct Keyring {
+    entries: Vec<KeyringEntry>,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    keys: Vec<ManifestEntry>,
+}
+
+#[derive(Deserialize)]
+struct ManifestEntry {
+    kid: String,
+    // relative to the keyring directory, `{kid}.pem` by default
+    file: Option<PathBuf>,
+    activates_at: Option<String>,
+    retires_at: Option<String>,
+}
+
+impl Keyring {
+    /// # Errors
+    /// When two keys share a `kid` or none of them is active now.
+    pub fn new(entries: Vec<KeyringEntry>) -> Result<Self, KeyringError> {
+        let mut kids = HashSet::new();
+        for entry in &entries {
+            if !kids.insert(entry.key.kid.clone()) {
+                return Err(KeyringError::DuplicateKid(
+                    entry.key.kid.clone().unwrap_or_default(),
+                ));
+            }
+        }
+
+        let keyring = Self { entries };
+        if keyring.active_key(Utc::now()).is_none() {
+            return Err(KeyringError::NoActiveKey);
+        }
+        Ok(keyring)
+    }
+
+    // A keyring with a single key that never retires
+    #[must_use]
+    pub fn single(key: SigningKey) -> Self {
+        Self {
+            entries: vec![KeyringEntry::new(key)],
+        }
+    }
+
+    /// Loads the keys listed in the directory's `keyring.json`.
+    ///
+    /// # Errors
+    /// When the manifest or a key can't be read, or the keyring is invalid.
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, KeyringError> {
+        let dir = dir.as_ref();
+        let manifest: Manifest =
+            serde_json::from_slice(&std::fs::read(dir.join(KEYRING_MANIFEST))?)?;
+
+        let entries = manifest
+            .keys
+            .into_iter()
+            .map(|entry| {
+                let file = entry
+                    .file
+                    .unwrap_or_else(|| PathBuf::from(format!("{}.pem", entry.kid)));
+                let key = SigningKey::load(&entry.kid, dir.join(file))
+                    .map_err(|e| KeyringError::Key(entry.kid.clone(), e))?;
+                let timestamp = |value: Option<String>| {
+                    value
+                        .map(|value| {
+                            DateTime::parse_from_rfc3339(&value)
+                                .map(|time| time.to_utc())
+                                .map_err(|_| KeyringError::InvalidTimestamp(entry.kid.clone()))
+                        })
+                        .transpose()
+                };
+
+                Ok(KeyringEntry {
+                    activates_at: timestamp(entry.activates_at)?.unwrap_or(DateTime::UNIX_EPOCH),
+                    retires_at: timestamp(entry.retires_at)?,
+                    key,
+                })
+            })
+            .collect::<Result<Vec<_>, KeyringError>>()?;
+
+        Self::new(entries)
+    }
+
+    /// `JWT_KEYRING_DIR`, else the single PEM key at `JWT_SIGNING_KEY_PATH`, else HMAC with `JWT_SECRET`.
+    ///
+    /// # Errors
+    /// When the configured keys can't be loaded.
+    pub fn from_env() -> Result<Self, KeyringError> {
+        if let Some(dir) = env_optional(env::JWT_KEYRING_DIR_ENV_VAR) {
+            return Self::load_dir(dir);
+        }
+
+        if let Some(path) = env_optional(env::JWT_SIGNING_KEY_PATH_ENV_VAR) {
+            // the file name is a fine default for the key id
+            let kid = env_optional(env::JWT_KEY_ID_ENV_VAR).unwrap_or_else(|| {
+                Path::new(&path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            });
+            let key = SigningKey::load(&kid, &path).map_err(|e| KeyringError::Key(kid, e))?;
+            return Ok(Self::single(key));
+        }
+
+        let secret = env_optional(env::JWT_SECRET_ENV_VAR).ok_or(KeyringError::MissingSecret)?;
+        Ok(Self::single(SigningKey::hmac(secret.as_bytes())))
+    }
+
+    // The most recently activated key that isn't retired yet
+    #[must_use]
+    pub fn active_key(&self, now: DateTime<Utc>) -> Option<&SigningKey> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.is_active(now))
+            .max_by_key(|entry| entry.activates_at)
+            .map(|entry| &entry.key)
+    }
+
+    // Key a token with this `kid` header has to be verified with
+    #[must_use]
+    pub fn verifying_key(&self, kid: Option<&str>, now: DateTime<Utc>) -> Option<&SigningKey> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key.kid.as_deref() == kid && entry.can_verify(now))
+            .map(|entry| &entry.key)
+    }
+
+    // Whether tokens signed right now can be verified by clients with the JWK set,
+    // a shared secret is never published
+    #[must_use]
+    pub fn publishes_active_key(&self, now: DateTime<Utc>) -> bool {
+        self.active_key(now).is_some_and(|key| key.jwk().is_some())
+    }
+
+    // Algorithms of the published keys that can still verify
+    #[must_use]
+    pub fn algorithms(&self, now: DateTime<Utc>) -> Vec<Algorithm> {
+        let mut algorithms = Vec::new();
+        for entry in self
+            .entries
+            .iter()
+            .filter(|entry| entry.can_verify(now) && entry.key.jwk().is_some())
+        {
+            if !algorithms.contains(&entry.key.algorithm) {
+                algorithms.push(entry.key.algorithm);
+            }
+        }
+        algorithms
+    }
+
+    // Public keys of every key that can still verify, upcoming ones included
+    #[must_use]
+    pub fn jwks(&self, now: DateTime<Utc>) -> JwkSet {
+        let keys = self
+            .entries
+            .iter()
+            .filter(|entry| entry.can_verify(now))
+            .filter_map(|entry| entry.key.jwk().cloned())
+            .collect();
+        JwkSet { keys }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RS256_PEM: &[u8] = include_bytes!("../../tests/fixtures/keys/rs256.pem");
+    const ES256_PEM: &[u8] = include_bytes!("../../tests/fixtures/keys/es256.pem");
+    const EDDSA_PEM: &[u8] = include_bytes!("../../tests/fixtures/keys/eddsa.pem");
+
+    fn entry(kid: &str, pem: &[u8], activates_at: i64, retires_at: Option<i64>) -> KeyringEntry {
+        KeyringEntry {
+            key: SigningKey::from_pem(kid, pem).unwrap(),
+            activates_at: DateTime::from_timestamp(activates_at, 0).unwrap(),
+            retires_at: retires_at.map(|at| DateTime::from_timestamp(at, 0).unwrap()),
+        }
+    }
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(timestamp, 0).unwrap()
+    }
+
+    fn kid(key: Option<&SigningKey>) -> Option<&str> {
+        key.and_then(|key| key.kid.as_deref())
+    }
+
+    #[test]
+    fn test_active_key_follows_rotation() {
+        let keyring = Keyring {
+            entries: vec![
+                entry("old", RS256_PEM, 0, Some(2_000)),
+                entry("current", ES256_PEM, 1_000, None),
+                entry("next", EDDSA_PEM, 3_000, None),
+            ],
+        };
+
+        assert_eq!(kid(keyring.active_key(at(500))), Some("old"));
+        // overlapping keys: the newest one signs
+        assert_eq!(kid(keyring.active_key(at(1_500))), Some("current"));
+        assert_eq!(kid(keyring.active_key(at(2_500))), Some("current"));
+        assert_eq!(kid(keyring.active_key(at(3_000))), Some("next"));
+    }
+
+    #[test]
+    fn test_retired_key_verifies_until_its_tokens_expire() {
+        let keyring = Keyring {
+            entries: vec![
+                entry("old", RS256_PEM, 0, Some(2_000)),
+                entry("current", ES256_PEM, 1_000, None),
+            ],
+        };
+
+        assert_eq!(
+            kid(keyring.verifying_key(Some("old"), at(2_000 + TOKEN_TTL_SECONDS - 1))),
+            Some("old")
+        );
+        assert!(keyring
+            .verifying_key(Some("old"), at(2_000 + TOKEN_TTL_SECONDS))
+            .is_none());
+        assert!(keyring.verifying_key(Some("unknown"), at(1_500)).is_none());
+        assert!(keyring.verifying_key(None, at(1_500)).is_none());
+    }
+
+    #[test]
+    fn test_jwks_publishes_upcoming_and_verifying_keys() {
+        let keyring = Keyring {
+            entries: vec![
+                entry("expired", RS256_PEM, 0, Some(1_000)),
+                entry("current", ES256_PEM, 1_000, None),
+                entry("next", EDDSA_PEM, 3_000, None),
+            ],
+        };
+
+        let kids: Vec<_> = keyring
+            .jwks(at(1_000 + TOKEN_TTL_SECONDS))
+            .keys
+            .into_iter()
+            .filter_map(|jwk| jwk.common.key_id)
+            .collect();
+        assert_eq!(kids, vec!["current".to_owned(), "next".to_owned()]);
+        assert_eq!(
+            keyring.algorithms(at(1_000 + TOKEN_TTL_SECONDS)),
+            vec![Algorithm::ES256, Algorithm::EdDSA]
+        );
+    }
+
+    #[test]
+    fn test_new_rejects_invalid_keyrings() {
+        assert!(matches!(
+            Keyring::new(vec![
+                entry("same", RS256_PEM, 0, None),
+                entry("same", ES256_PEM, 0, None),
+            ]),
+            Err(KeyringError::DuplicateKid(_))
+        ));
+        assert!(matches!(
+            Keyring::new(vec![entry("retired", RS256_PEM, 0, Some(1_000))]),
+            Err(KeyringError::NoActiveKey)
+        ));
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let keyring = Keyring::load_dir("tests/fixtures/keyring").unwrap();
+
+        assert_eq!(kid(keyring.active_key(Utc::now())), Some("2025-06"));
+        assert_eq!(
+            kid(keyring.verifying_key(Some("2025-01"), at(1_750_000_000))),
+            Some("2025-01")
+        );
+        assert_eq!(keyring.jwks(Utc::now()).keys.len(), 1);
+    }
+}
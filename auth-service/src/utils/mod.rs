@@ -1,2 +1,6 @@
 pub mod auth;
 pub mod constants;
+pub mod email;
+pub mod keyring;
+pub mod oidc;
+pub mod signing_key;
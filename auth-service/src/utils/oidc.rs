@@ -0,0 +1,191 @@
+use chrono::Utc;
+use jsonwebtoken::Algorithm;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::AuthorizationGrant;
+
+use super::{
+    auth::{create_token, GenerateTokenError, TOKEN_TTL_SECONDS},
+    constants::{APP_URL, DEVICE_CODE_GRANT_TYPE, JWT_ISSUER},
+    keyring::Keyring,
+};
+
+// Claims of an OIDC ID token, meant for the client that asked for it rather than for APIs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    // the client_id
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    pub auth_time: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    pub amr: Vec<String>,
+    // with the `email` scope only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+// ID token of the user who granted the authorization code
+pub fn generate_id_token(
+    grant: &AuthorizationGrant,
+    keyring: &Keyring,
+) -> Result<String, GenerateTokenError> {
+    let iat =
+        usize::try_from(Utc::now().timestamp()).map_err(|_| GenerateTokenError::UnexpectedError)?;
+    let ttl =
+        usize::try_from(TOKEN_TTL_SECONDS).map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    let claims = IdTokenClaims {
+        iss: JWT_ISSUER.to_owned(),
+        sub: grant.email.as_ref().to_owned(),
+        aud: grant.client_id.clone(),
+        exp: iat + ttl,
+        iat,
+        auth_time: grant.authentication.auth_time,
+        nonce: grant.nonce.clone(),
+        amr: grant.authentication.amr.clone(),
+        email: grant
+            .has_scope("email")
+            .then(|| grant.email.as_ref().to_owned()),
+    };
+    create_token(&claims, keyring)
+}
+
+// Discovery document, lets OIDC client libraries configure themselves from the issuer URL
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    // RFC 8628
+    pub device_authorization_endpoint: String,
+    // RFC 7662 and RFC 7009
+    pub introspection_endpoint: String,
+    pub revocation_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<Algorithm>,
+    pub scopes_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+}
+
+impl OpenIdConfiguration {
+    #[must_use]
+    pub fn new(keyring: &Keyring) -> Self {
+        let base_url = APP_URL.trim_end_matches('/');
+        let strings = |values: &[&str]| values.iter().map(|&value| value.to_owned()).collect();
+
+        Self {
+            issuer: JWT_ISSUER.to_owned(),
+            authorization_endpoint: format!("{base_url}/authorize"),
+            token_endpoint: format!("{base_url}/token"),
+            device_authorization_endpoint: format!("{base_url}/device_authorization"),
+            introspection_endpoint: format!("{base_url}/introspect"),
+            revocation_endpoint: format!("{base_url}/revoke"),
+            userinfo_endpoint: format!("{base_url}/userinfo"),
+            jwks_uri: format!("{base_url}/.well-known/jwks.json"),
+            response_types_supported: strings(&["code"]),
+            grant_types_supported: strings(&[
+                "authorization_code",
+                "client_credentials",
+                DEVICE_CODE_GRANT_TYPE,
+            ]),
+            subject_types_supported: strings(&["public"]),
+            id_token_signing_alg_values_supported: keyring.algorithms(Utc::now()),
+            // ID tokens are only issued when clients can verify them, see `Keyring::publishes_active_key`
+            scopes_supported: if keyring.publishes_active_key(Utc::now()) {
+                strings(&["openid", "email"])
+            } else {
+                strings(&["email"])
+            },
+            claims_supported: strings(&[
+                "iss",
+                "sub",
+                "aud",
+                "exp",
+                "iat",
+                "auth_time",
+                "nonce",
+                "amr",
+                "email",
+            ]),
+            code_challenge_methods_supported: strings(&["S256"]),
+            token_endpoint_auth_methods_supported: strings(&[
+                "none",
+                "client_secret_basic",
+                "client_secret_post",
+            ]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{decode, Validation};
+
+    use crate::{
+        domain::{Authentication, CodeChallenge, Email},
+        utils::signing_key::SigningKey,
+    };
+
+    use super::*;
+
+    fn grant(scope: &str) -> AuthorizationGrant {
+        AuthorizationGrant {
+            client_id: "spa".to_owned(),
+            redirect_uri: "http://localhost:8000/callback".to_owned(),
+            email: Email::parse("test@example.com").unwrap(),
+            code_challenge: CodeChallenge::parse(
+                "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM",
+                Some("S256"),
+            )
+            .unwrap(),
+            scope: Some(scope.to_owned()),
+            nonce: Some("n-0S6_WzA2Mj".to_owned()),
+            authentication: Authentication {
+                auth_time: 1_700_000_000,
+                amr: vec!["pwd".to_owned(), "otp".to_owned(), "mfa".to_owned()],
+            },
+        }
+    }
+
+    fn decode_id_token(token: &str, key: &SigningKey) -> IdTokenClaims {
+        let mut validation = Validation::new(key.algorithm);
+        validation.set_audience(&["spa"]);
+        decode::<IdTokenClaims>(token, key.decoding_key(), &validation)
+            .unwrap()
+            .claims
+    }
+
+    #[test]
+    fn test_generate_id_token() {
+        let key = SigningKey::hmac(b"secret");
+        let token = generate_id_token(&grant("openid"), &Keyring::single(key.clone())).unwrap();
+
+        let claims = decode_id_token(&token, &key);
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.iss, *JWT_ISSUER);
+        assert_eq!(claims.auth_time, 1_700_000_000);
+        assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+        assert_eq!(claims.amr, vec!["pwd", "otp", "mfa"]);
+        assert!(claims.email.is_none());
+    }
+
+    #[test]
+    fn test_generate_id_token_with_email_scope() {
+        let key = SigningKey::hmac(b"secret");
+        let token =
+            generate_id_token(&grant("openid email"), &Keyring::single(key.clone())).unwrap();
+
+        let claims = decode_id_token(&token, &key);
+        assert_eq!(claims.email.as_deref(), Some("test@example.com"));
+    }
+}
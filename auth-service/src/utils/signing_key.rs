@@ -0,0 +1,267 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::path::Path;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header,
+};
+use ring::{
+    rand::SystemRandom,
+    rsa::PublicKeyComponents,
+    signature::{self, EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair},
+};
+use serde::Serialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SigningKeyError {
+    #[error("Unable to read key: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid PEM: {0}")]
+    Pem(#[from] pem::PemError),
+    #[error("Unsupported key, expected an RSA, P-256 or Ed25519 private key")]
+    UnsupportedKey,
+    #[error("Invalid key: {0}")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+}
+
+// Key the JWTs are signed with. Asymmetric keys publish their public part as a JWK
+// so other services can verify tokens without sharing any secret.
+#[derive(Clone)]
+pub struct SigningKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+impl SigningKey {
+    // Shared secret, HS256
+    #[must_use]
+    pub fn hmac(secret: &[u8]) -> Self {
+        Self {
+            kid: None,
+            algorithm: Algorithm::HS256,
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    /// Private key in PEM, PKCS#8 for all algorithms or PKCS#1 for RSA.
+    /// The algorithm follows from the key: RS256 for RSA, ES256 for P-256 and `EdDSA` for Ed25519.
+    ///
+    /// # Errors
+    /// When the PEM is invalid or holds another kind of key.
+    pub fn from_pem(kid: &str, pem: &[u8]) -> Result<Self, SigningKeyError> {
+        let der = pem::parse(pem)?;
+
+        let (algorithm, encoding, params) = if der.tag() == "RSA PRIVATE KEY" {
+            let key_pair = RsaKeyPair::from_der(der.contents())
+                .map_err(|_| SigningKeyError::UnsupportedKey)?;
+            (
+                Algorithm::RS256,
+                EncodingKey::from_rsa_pem(pem)?,
+                rsa_parameters(&key_pair),
+            )
+        } else if let Ok(key_pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der.contents()) {
+            (
+                Algorithm::EdDSA,
+                EncodingKey::from_ed_pem(pem)?,
+                AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+                }),
+            )
+        } else if let Ok(key_pair) = EcdsaKeyPair::from_pkcs8(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            der.contents(),
+            &SystemRandom::new(),
+        ) {
+            // uncompressed point: 0x04 || x || y
+            let point = key_pair.public_key().as_ref();
+            (
+                Algorithm::ES256,
+                EncodingKey::from_ec_pem(pem)?,
+                AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                    key_type: EllipticCurveKeyType::EC,
+                    curve: EllipticCurve::P256,
+                    x: URL_SAFE_NO_PAD.encode(&point[1..33]),
+                    y: URL_SAFE_NO_PAD.encode(&point[33..]),
+                }),
+            )
+        } else if let Ok(key_pair) = RsaKeyPair::from_pkcs8(der.contents()) {
+            (
+                Algorithm::RS256,
+                EncodingKey::from_rsa_pem(pem)?,
+                rsa_parameters(&key_pair),
+            )
+        } else {
+            return Err(SigningKeyError::UnsupportedKey);
+        };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm(algorithm)),
+                key_id: Some(kid.to_owned()),
+                ..CommonParameters::default()
+            },
+            algorithm: params,
+        };
+
+        Ok(Self {
+            kid: Some(kid.to_owned()),
+            algorithm,
+            encoding,
+            decoding: DecodingKey::from_jwk(&jwk)?,
+            jwk: Some(jwk),
+        })
+    }
+
+    /// # Errors
+    /// When the file can't be read or doesn't hold a supported private key.
+    pub fn load(kid: &str, path: impl AsRef<Path>) -> Result<Self, SigningKeyError> {
+        Self::from_pem(kid, &std::fs::read(path)?)
+    }
+
+    /// Signs `claims`, the header names the algorithm and the `kid`.
+    ///
+    /// # Errors
+    /// When the claims can't be serialized or signing fails.
+    pub fn sign(&self, claims: &impl Serialize) -> Result<String, jsonwebtoken::errors::Error> {
+        let header = Header {
+            kid: self.kid.clone(),
+            ..Header::new(self.algorithm)
+        };
+        jsonwebtoken::encode(&header, claims, &self.encoding)
+    }
+
+    #[must_use]
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding
+    }
+
+    // Public key to publish, there is none for a shared secret
+    #[must_use]
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+}
+
+fn rsa_parameters(key_pair: &RsaKeyPair) -> AlgorithmParameters {
+    let components = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+    AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(components.n),
+        e: URL_SAFE_NO_PAD.encode(components.e),
+    })
+}
+
+fn key_algorithm(algorithm: Algorithm) -> KeyAlgorithm {
+    match algorithm {
+        Algorithm::ES256 => KeyAlgorithm::ES256,
+        Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+        _ => KeyAlgorithm::RS256,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{decode, decode_header, Validation};
+    use serde::Deserialize;
+
+    use super::*;
+
+    const RS256_PEM: &[u8] = include_bytes!("../../tests/fixtures/keys/rs256.pem");
+    const ES256_PEM: &[u8] = include_bytes!("../../tests/fixtures/keys/es256.pem");
+    const EDDSA_PEM: &[u8] = include_bytes!("../../tests/fixtures/keys/eddsa.pem");
+    const ES384_PEM: &[u8] = include_bytes!("../../tests/fixtures/keys/es384.pem");
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims {
+            sub: "test@example.com".to_owned(),
+            exp: usize::MAX / 2,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_with_published_key() {
+        for (pem, algorithm) in [
+            (RS256_PEM, Algorithm::RS256),
+            (ES256_PEM, Algorithm::ES256),
+            (EDDSA_PEM, Algorithm::EdDSA),
+        ] {
+            let key = SigningKey::from_pem("test-key", pem).unwrap();
+            assert_eq!(key.algorithm, algorithm);
+
+            let token = key.sign(&claims()).unwrap();
+            let header = decode_header(&token).unwrap();
+            assert_eq!(header.alg, algorithm);
+            assert_eq!(header.kid.as_deref(), Some("test-key"));
+
+            // only what is published is needed to verify
+            let jwk = key.jwk().unwrap();
+            assert_eq!(jwk.common.key_id.as_deref(), Some("test-key"));
+            let decoded = decode::<TestClaims>(
+                &token,
+                &DecodingKey::from_jwk(jwk).unwrap(),
+                &Validation::new(algorithm),
+            )
+            .unwrap();
+            assert_eq!(decoded.claims, claims());
+        }
+    }
+
+    #[test]
+    fn test_jwk_has_no_private_part() {
+        let key = SigningKey::from_pem("test-key", RS256_PEM).unwrap();
+        let jwk = serde_json::to_value(key.jwk().unwrap()).unwrap();
+
+        assert_eq!(jwk["kty"], "RSA");
+        assert_eq!(jwk["alg"], "RS256");
+        assert_eq!(jwk["use"], "sig");
+        assert_eq!(jwk["e"], "AQAB");
+        assert!(jwk.get("d").is_none());
+    }
+
+    #[test]
+    fn test_hmac_key_is_not_published() {
+        let key = SigningKey::hmac(b"secret");
+        let token = key.sign(&claims()).unwrap();
+
+        assert!(key.jwk().is_none());
+        assert!(decode_header(&token).unwrap().kid.is_none());
+        assert!(decode::<TestClaims>(
+            &token,
+            key.decoding_key(),
+            &Validation::new(Algorithm::HS256)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_unsupported_key() {
+        assert!(matches!(
+            SigningKey::from_pem("test-key", ES384_PEM),
+            Err(SigningKeyError::UnsupportedKey)
+        ));
+        assert!(matches!(
+            SigningKey::from_pem("test-key", b"not a pem"),
+            Err(SigningKeyError::Pem(_))
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+use crate::helpers::{get_error, login, signing_key, signup, TestApp, TestUser};
+use auth_service::utils::{
+    auth::Claims,
+    constants::{JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_ISSUER, REFRESH_TOKEN_COOKIE_NAME},
+};
+use chrono::Utc;
+use jsonwebtoken::{decode, Algorithm, Validation};
+use reqwest::Url;
+
+const NEW_PASSWORD: &str = "Sl0w-m0ving tortoise w1ns";
+
+fn auth_token(response: &reqwest::Response) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+async fn change_password(app: &TestApp, current: &str, new: &str) -> reqwest::Response {
+    app.post_change_password(&serde_json::json!({
+        "currentPassword": current,
+        "newPassword": new
+    }))
+    .await
+}
+
+async fn token_status(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+
+    let response = change_password(&app, &user.password, NEW_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(get_error(response).await, "Missing token".to_owned());
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let _ = login(&app, &user).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({ "newPassword": NEW_PASSWORD }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[tokio::test]
+async fn should_return_401_if_current_password_is_wrong() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let _ = login(&app, &user).await;
+
+    let response = change_password(&app, "not-the-password", NEW_PASSWORD).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_return_400_if_new_password_is_weak() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let _ = login(&app, &user).await;
+
+    let response = change_password(&app, &user.password, "password123").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(get_error(response).await, "Password is too weak".to_owned());
+}
+
+#[tokio::test]
+async fn should_return_200_and_replace_password() {
+    let app = TestApp::new().await;
+    let mut user = TestUser::random(false);
+    signup(&app, &user).await;
+    let _ = login(&app, &user).await;
+
+    let response = change_password(&app, &user.password, NEW_PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // the caller stays logged in with a fresh token
+    assert_eq!(token_status(&app, &auth_token(&response)).await, 200);
+
+    let old_login = app
+        .post_login(&serde_json::json!({ "email": user.email, "password": user.password }))
+        .await;
+    assert_eq!(old_login.status().as_u16(), 401);
+
+    user.password = NEW_PASSWORD.to_owned();
+    let _ = login(&app, &user).await;
+}
+
+#[tokio::test]
+async fn should_revoke_other_sessions() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let other_login = login(&app, &user).await;
+    let other_session = auth_token(&other_login);
+    let other_refresh_token = other_login
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    let _ = login(&app, &user).await;
+
+    let response = change_password(&app, &user.password, NEW_PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(token_status(&app, &other_session).await, 401);
+    assert_eq!(token_status(&app, &auth_token(&response)).await, 200);
+
+    // nor can the other session get a new JWT
+    app.cookie_jar.add_cookie_str(
+        &format!("{REFRESH_TOKEN_COOKIE_NAME}={other_refresh_token}; Path=/"),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+    assert_eq!(app.post_refresh().await.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_keep_how_the_user_logged_in() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+
+    // a session from an hour ago, logged in with a second factor
+    let now = usize::try_from(Utc::now().timestamp()).unwrap();
+    let claims = Claims {
+        sub: user.email.clone(),
+        exp: now + 600,
+        iat: now - 3_600,
+        jti: "earlier-login".to_owned(),
+        iss: JWT_ISSUER.to_owned(),
+        aud: JWT_AUDIENCE.to_owned(),
+        auth_time: Some(now - 3_600),
+        amr: vec!["pwd".to_owned(), "otp".to_owned(), "mfa".to_owned()],
+        client_id: None,
+        scope: None,
+    };
+    let token = signing_key().sign(&claims).unwrap();
+
+    let response = app
+        .post_with_bearer(
+            "/change-password",
+            &token,
+            &serde_json::json!({
+                "currentPassword": user.password,
+                "newPassword": NEW_PASSWORD
+            }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.set_audience(&[JWT_AUDIENCE.as_str()]);
+    let renewed = decode::<Claims>(
+        &auth_token(&response),
+        signing_key().decoding_key(),
+        &validation,
+    )
+    .unwrap()
+    .claims;
+    assert_eq!(renewed.auth_time, claims.auth_time);
+    assert_eq!(renewed.amr, claims.amr);
+}
+
+#[tokio::test]
+async fn should_accept_bearer_token() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let token = auth_token(&login(&app, &user).await);
+
+    let response = app
+        .post_with_bearer(
+            "/change-password",
+            &token,
+            &serde_json::json!({
+                "currentPassword": user.password,
+                "newPassword": NEW_PASSWORD
+            }),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -0,0 +1,161 @@
+use auth_service::{routes::TokenResponse, utils::auth::Claims};
+use jsonwebtoken::{decode, Algorithm, Validation};
+
+use crate::{
+    helpers::{signing_key, TestApp},
+    oauth::{oauth_error, CLIENT_ID},
+};
+
+pub const SERVICE_ID: &str = "test-service";
+pub const SERVICE_SECRET: &str = "test-client-secret";
+
+// Access token of the test service, with all of its scopes
+pub async fn service_token(app: &TestApp) -> String {
+    let response = app
+        .post_token_with_basic(
+            SERVICE_ID,
+            SERVICE_SECRET,
+            &[("grant_type", "client_credentials")],
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json::<TokenResponse>().await.unwrap().access_token
+}
+
+fn access_token_claims(access_token: &str) -> Claims {
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.validate_aud = false;
+
+    decode::<Claims>(access_token, signing_key().decoding_key(), &validation)
+        .expect("Invalid access token")
+        .claims
+}
+
+#[tokio::test]
+async fn should_issue_token_to_client_with_basic_auth() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_token_with_basic(
+            SERVICE_ID,
+            SERVICE_SECRET,
+            &[("grant_type", "client_credentials")],
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+    let token = response.json::<TokenResponse>().await.unwrap();
+    assert_eq!(token.scope.as_deref(), Some("introspect read"));
+    assert!(token.id_token.is_none());
+
+    let claims = access_token_claims(&token.access_token);
+    assert_eq!(claims.sub, SERVICE_ID);
+    assert_eq!(claims.client_id.as_deref(), Some(SERVICE_ID));
+    assert!(claims.has_scope("introspect"));
+
+    // a machine token is not a login session
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_with_bearer("/logout", &token.access_token, &serde_json::json!({}))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_issue_token_with_requested_scope_to_client_with_form_auth() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_token(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", SERVICE_ID),
+            ("client_secret", SERVICE_SECRET),
+            ("scope", "read"),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response.json::<TokenResponse>().await.unwrap();
+    assert_eq!(token.scope.as_deref(), Some("read"));
+    assert!(!access_token_claims(&token.access_token).has_scope("introspect"));
+}
+
+#[tokio::test]
+async fn should_return_401_if_client_secret_is_wrong() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_token_with_basic(
+            SERVICE_ID,
+            "wrong-client-secret",
+            &[("grant_type", "client_credentials")],
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(response.headers().get("www-authenticate").unwrap(), "Basic");
+    assert_eq!(oauth_error(response).await, "invalid_client");
+
+    let response = app
+        .post_token(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", SERVICE_ID),
+        ])
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(oauth_error(response).await, "invalid_client");
+}
+
+#[tokio::test]
+async fn should_return_401_for_unknown_client() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_token_with_basic(
+            "unknown-service",
+            SERVICE_SECRET,
+            &[("grant_type", "client_credentials")],
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(oauth_error(response).await, "invalid_client");
+}
+
+#[tokio::test]
+async fn should_reject_public_client() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_token(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", CLIENT_ID),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "unauthorized_client");
+}
+
+#[tokio::test]
+async fn should_reject_scope_not_registered_for_client() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_token_with_basic(
+            SERVICE_ID,
+            SERVICE_SECRET,
+            &[
+                ("grant_type", "client_credentials"),
+                ("scope", "read admin"),
+            ],
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_scope");
+}
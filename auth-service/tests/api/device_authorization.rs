@@ -0,0 +1,248 @@
+use auth_service::{
+    routes::{DeviceAuthorizationResponse, TokenResponse},
+    utils::{
+        auth::Claims,
+        constants::{APP_URL, DEVICE_CODE_GRANT_TYPE},
+    },
+};
+use jsonwebtoken::{decode, Algorithm, Validation};
+
+use crate::{
+    helpers::{login, signing_key, signup, TestApp, TestUser},
+    oauth::{oauth_error, CLIENT_ID},
+};
+
+async fn start(app: &TestApp) -> DeviceAuthorizationResponse {
+    let response = app
+        .post_device_authorization(&[("client_id", CLIENT_ID), ("scope", "read")])
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<DeviceAuthorizationResponse>()
+        .await
+        .unwrap()
+}
+
+async fn poll(app: &TestApp, device_code: &str) -> reqwest::Response {
+    app.post_token(&[
+        ("grant_type", DEVICE_CODE_GRANT_TYPE),
+        ("device_code", device_code),
+        ("client_id", CLIENT_ID),
+    ])
+    .await
+}
+
+// The user logs in on the verification page and answers for the device
+async fn answer(app: &TestApp, user: &TestUser, user_code: &str, approved: bool) {
+    signup(app, user).await;
+    let _ = login(app, user).await;
+
+    let response = app
+        .post_device_verify(&serde_json::json!({ "userCode": user_code, "approved": approved }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_device_and_user_codes() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_device_authorization(&[("client_id", CLIENT_ID)])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+    let started = response
+        .json::<DeviceAuthorizationResponse>()
+        .await
+        .unwrap();
+    assert_eq!(started.device_code.len(), 64);
+    assert_eq!(started.user_code.len(), 9);
+    assert_eq!(
+        started.verification_uri,
+        format!("{}/device", APP_URL.trim_end_matches('/'))
+    );
+    assert_eq!(
+        started.verification_uri_complete,
+        format!(
+            "{}?user_code={}",
+            started.verification_uri, started.user_code
+        )
+    );
+    assert_eq!(started.interval, 5);
+}
+
+#[tokio::test]
+async fn should_return_401_for_unknown_client() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_device_authorization(&[("client_id", "unknown-client")])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(oauth_error(response).await, "invalid_client");
+}
+
+#[tokio::test]
+async fn should_return_400_if_scope_not_registered() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_device_authorization(&[("client_id", CLIENT_ID), ("scope", "read admin")])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_scope");
+}
+
+#[tokio::test]
+async fn should_serve_verification_page() {
+    let app = TestApp::new().await;
+
+    let response = app.get_device().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("device-form"));
+}
+
+#[tokio::test]
+async fn should_send_user_code_to_login_page() {
+    let app = TestApp::new().await;
+    let started = start(&app).await;
+
+    // typed in by hand
+    let response = app
+        .get_device_verify(&started.user_code.replace('-', "").to_lowercase())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 303);
+    let location = response
+        .headers()
+        .get("location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert_eq!(
+        location,
+        format!("/?device_user_code={}", started.user_code)
+    );
+
+    // the consent page gets the client from the server, not from its URL
+    let response = app.get_device_request(&started.user_code).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let pending = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(pending["name"], "Test App");
+    assert_eq!(pending["scope"], "read");
+}
+
+#[tokio::test]
+async fn should_send_unknown_user_code_back_to_verification_page() {
+    let app = TestApp::new().await;
+
+    for user_code in ["BCDF-GHJK", "not a code"] {
+        let response = app.get_device_verify(user_code).await;
+
+        assert_eq!(response.status().as_u16(), 303);
+        assert_eq!(
+            response.headers().get("location").unwrap(),
+            "/device?error=invalid_request"
+        );
+    }
+}
+
+#[tokio::test]
+async fn should_return_400_for_unknown_device_request() {
+    let app = TestApp::new().await;
+
+    let response = app.get_device_request("BCDF-GHJK").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_request");
+}
+
+#[tokio::test]
+async fn should_issue_token_once_user_approves() {
+    let app = TestApp::new().await;
+    let started = start(&app).await;
+
+    let response = poll(&app, &started.device_code).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "authorization_pending");
+
+    let user = TestUser::random(false);
+    answer(&app, &user, &started.user_code, true).await;
+
+    let response = poll(&app, &started.device_code).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response.json::<TokenResponse>().await.unwrap();
+    assert_eq!(token.scope.as_deref(), Some("read"));
+
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.validate_aud = false;
+    let claims = decode::<Claims>(
+        &token.access_token,
+        signing_key().decoding_key(),
+        &validation,
+    )
+    .unwrap()
+    .claims;
+    assert_eq!(claims.sub, user.email);
+    assert_eq!(claims.client_id.as_deref(), Some(CLIENT_ID));
+
+    // a device code is redeemed once
+    let response = poll(&app, &started.device_code).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+}
+
+#[tokio::test]
+async fn should_return_access_denied_if_user_denies() {
+    let app = TestApp::new().await;
+    let started = start(&app).await;
+
+    answer(&app, &TestUser::random(false), &started.user_code, false).await;
+
+    let response = poll(&app, &started.device_code).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "access_denied");
+}
+
+#[tokio::test]
+async fn should_return_400_if_answered_without_login() {
+    let app = TestApp::new().await;
+    let started = start(&app).await;
+
+    let response = app
+        .post_device_verify(&serde_json::json!({ "userCode": started.user_code, "approved": true }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let response = poll(&app, &started.device_code).await;
+    assert_eq!(oauth_error(response).await, "authorization_pending");
+}
+
+#[tokio::test]
+async fn should_reject_device_code_of_another_client() {
+    let app = TestApp::new().await;
+    let started = start(&app).await;
+    answer(&app, &TestUser::random(false), &started.user_code, true).await;
+
+    let response = app
+        .post_token_with_basic(
+            "test-service",
+            "test-client-secret",
+            &[
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                ("device_code", started.device_code.as_str()),
+            ],
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+    // the grant is still there for the device that asked for it
+    let response = poll(&app, &started.device_code).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -0,0 +1,64 @@
+use crate::helpers::{get_random_email, request_reset_token, signup, TestApp, TestUser};
+use auth_service::{domain::Email, routes::ForgotPasswordResponse};
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let app = TestApp::new().await;
+    let response = app
+        .post_forgot_password(&serde_json::json!({ "mail": get_random_email() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_email() {
+    let app = TestApp::new().await;
+    let response = app
+        .post_forgot_password(&serde_json::json!({ "email": "not_an_email" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_send_reset_link_to_existing_user() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+
+    let token = request_reset_token(&app, &user.email).await;
+
+    assert_eq!(token.len(), 64);
+    // only the hash of the token is kept
+    let tokens = app.password_reset_token_store.read().await;
+    let tokens = tokens.tokens.lock().unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert!(!tokens.contains_key(&token));
+}
+
+#[tokio::test]
+async fn should_not_reveal_if_email_exists() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let unknown = get_random_email();
+
+    let mut bodies = vec![];
+    for email in [&user.email, &unknown] {
+        let response = app
+            .post_forgot_password(&serde_json::json!({ "email": email }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        let body = response
+            .json::<ForgotPasswordResponse>()
+            .await
+            .expect("Could not deserialize response body to ForgotPasswordResponse");
+        bodies.push(body.message);
+    }
+
+    assert_eq!(bodies[0], bodies[1]);
+    assert!(app
+        .email_client
+        .last_sent_to(&Email::parse(&unknown).unwrap())
+        .is_none());
+}
@@ -1,14 +1,30 @@
+// The original request helpers borrow their URLs
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use std::sync::Arc;
 
 use auth_service::{
-    app_state::{AppState, BannedTokensType, UserStoreType},
-    domain::{Email, Password, User},
-    services::{HashmapUserStore, HashsetBannedTokenStore},
-    utils::constants::test,
+    app_state::{
+        AppState, AuthorizationCodeStoreType, BannedTokensType, ClientStoreType,
+        DeviceCodeStoreType, PasswordResetTokenStoreType, RefreshTokenStoreType,
+        TwoFACodeStoreType, UserStoreType,
+    },
+    domain::{BreachedPasswords, Email, HashedPassword, Password, PasswordPolicy, User},
+    services::{
+        HashmapAuthorizationCodeStore, HashmapClientStore, HashmapDeviceCodeStore,
+        HashmapPasswordResetTokenStore, HashmapRefreshTokenStore, HashmapTwoFACodeStore,
+        HashmapUserStore, HashsetBannedTokenStore, MockEmailClient,
+    },
+    utils::{
+        constants::{test, ARGON2_PARAMS, DEVICE_CODE_TTL_SECONDS},
+        keyring::Keyring,
+        signing_key::SigningKey,
+    },
     Application, ErrorResponse,
 };
 
 use auth_service::domain::UserStore;
+use chrono::Duration;
 use reqwest::cookie::Jar;
 use tokio::sync::RwLock;
 
@@ -16,31 +32,85 @@ pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
     pub http_client: reqwest::Client,
-    pub banned_tokens: BannedTokensType,
+    pub user_store: UserStoreType,
+    pub two_fa_code_store: TwoFACodeStoreType,
+    // kept concrete so tests can look inside it
+    pub password_reset_token_store: Arc<RwLock<HashmapPasswordResetTokenStore>>,
+    pub email_client: MockEmailClient,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::with_keyring(Keyring::single(signing_key())).await
+    }
+
+    // The default user store, signing with `keyring`
+    pub async fn with_keyring(keyring: Keyring) -> Self {
         let mut mock_store = HashmapUserStore::default();
 
+        let password = Password::parse("!@#(*$&#!234234alsdkj!@#").unwrap();
         let _existing_user = User::new(
-            Email::parse("existing@user.com").unwrap().as_ref(),
-            Password::parse("!@#(*$&#!234234alsdkj!@#")
-                .unwrap()
-                .as_ref(),
+            Email::parse("existing@user.com").unwrap(),
+            HashedPassword::hash(&password, ARGON2_PARAMS.clone())
+                .await
+                .expect("unable to hash password for tests"),
             true,
-        )
-        .expect("unable to created existing user for tests");
+        );
 
         mock_store
             .add_user(_existing_user)
             .await
             .expect("unable to add mock user");
 
-        let user_store: UserStoreType = Arc::new(RwLock::new(mock_store));
+        Self::build(Arc::new(RwLock::new(mock_store)), keyring).await
+    }
+
+    // Runs the app on top of any user store, e.g. a test double
+    pub async fn with_user_store(user_store: UserStoreType) -> Self {
+        Self::build(user_store, Keyring::single(signing_key())).await
+    }
+
+    async fn build(user_store: UserStoreType, keyring: Keyring) -> Self {
         let banned_tokens: BannedTokensType =
             Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let mock_state = AppState::new(user_store, banned_tokens.clone());
+        let two_fa_code_store: TwoFACodeStoreType =
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+        let password_reset_token_store =
+            Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default()));
+        let refresh_token_store: RefreshTokenStoreType =
+            Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let client_store: ClientStoreType = Arc::new(RwLock::new(
+            HashmapClientStore::load("tests/fixtures/oauth_clients.json")
+                .expect("unable to load OAuth clients fixture"),
+        ));
+        let authorization_code_store: AuthorizationCodeStoreType =
+            Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default()));
+        // devices may poll back to back, `slow_down` is left to the store's own tests
+        let device_code_store: DeviceCodeStoreType =
+            Arc::new(RwLock::new(HashmapDeviceCodeStore::new(
+                Duration::seconds(DEVICE_CODE_TTL_SECONDS),
+                Duration::zero(),
+            )));
+        let email_client = MockEmailClient::default();
+        let breached = BreachedPasswords::load("tests/fixtures/breached_passwords.txt")
+            .expect("unable to load breached passwords fixture");
+        let password_policy = PasswordPolicy {
+            breached: Some(Arc::new(breached)),
+            ..PasswordPolicy::default()
+        };
+        let mock_state = AppState::new(
+            user_store.clone(),
+            banned_tokens,
+            two_fa_code_store.clone(),
+            password_reset_token_store.clone() as PasswordResetTokenStoreType,
+            refresh_token_store,
+            client_store,
+            authorization_code_store,
+            device_code_store,
+            Arc::new(email_client.clone()),
+            Arc::new(password_policy),
+            Arc::new(RwLock::new(keyring)),
+        );
 
         let app = Application::build(mock_state, test::APP_ADDRESS)
             .await
@@ -61,7 +131,10 @@ impl TestApp {
             address,
             cookie_jar,
             http_client,
-            banned_tokens,
+            user_store,
+            two_fa_code_store,
+            password_reset_token_store,
+            email_client,
         }
     }
 
@@ -72,6 +145,34 @@ impl TestApp {
             .await
             .expect("Failed to execute request")
     }
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_openid_configuration(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/.well-known/openid-configuration",
+                &self.address
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_userinfo(&self, access_token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/userinfo", &self.address))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .expect("Failed to execute get userinfo request")
+    }
+
     pub async fn post_signup<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -96,25 +197,233 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
-    pub async fn post_route(&self, route: &str) -> reqwest::Response {
-        dbg!(&self.address);
+    pub async fn post_logout<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
         self.http_client
-            .post(&format!("{}{}", &self.address, &route))
+            .post(&format!("{}/logout", &self.address))
+            .json(body)
             .send()
             .await
-            .expect(format!("Familed to execute request to route: {:?}", route).as_str())
+            .expect("Failed to execute post logout request")
     }
 
-    pub async fn post_logout<Body>(&self, body: &Body) -> reqwest::Response
+    pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/verify-2fa", &self.address))
             .json(body)
             .send()
             .await
-            .expect("Failed to execute post logout request")
+            .expect("Failed to execute post verify 2fa request")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute post change password request")
+    }
+
+    pub async fn post_forgot_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/forgot-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute post forgot password request")
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/reset-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute post reset password request")
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute post refresh request")
+    }
+
+    // Authenticates like an API client would: a Bearer token and no cookies
+    pub async fn post_with_bearer<Body>(
+        &self,
+        path: &str,
+        token: &str,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        reqwest::Client::new()
+            .post(format!("{}{}", &self.address, path))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute post request with bearer token")
+    }
+
+    // Redirects are not followed, tests look at where `/authorize` sends the browser
+    pub async fn get_authorize(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(format!("{}/authorize", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute get authorize request")
+    }
+
+    pub async fn get_client(&self, client_id: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/clients/{}", &self.address, client_id))
+            .send()
+            .await
+            .expect("Failed to execute get client request")
+    }
+
+    pub async fn post_consent<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/authorize", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute post consent request")
+    }
+
+    pub async fn post_token<Form>(&self, form: &Form) -> reqwest::Response
+    where
+        Form: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/token", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute post token request")
+    }
+
+    // Authenticates the client with client_secret_basic
+    pub async fn post_token_with_basic<Form>(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        form: &Form,
+    ) -> reqwest::Response
+    where
+        Form: serde::Serialize,
+    {
+        reqwest::Client::new()
+            .post(format!("{}/token", &self.address))
+            .basic_auth(client_id, Some(client_secret))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute post token request with basic auth")
+    }
+
+    pub async fn post_introspect<Form>(&self, form: &Form) -> reqwest::Response
+    where
+        Form: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/introspect", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute post introspect request")
+    }
+
+    pub async fn post_revoke<Form>(&self, form: &Form) -> reqwest::Response
+    where
+        Form: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/revoke", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute post revoke request")
+    }
+
+    pub async fn post_device_authorization<Form>(&self, form: &Form) -> reqwest::Response
+    where
+        Form: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/device_authorization", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute post device authorization request")
+    }
+
+    pub async fn get_device(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/device", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute get device request")
+    }
+
+    // Redirects are not followed, like `get_authorize`
+    pub async fn get_device_verify(&self, user_code: &str) -> reqwest::Response {
+        reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(format!("{}/device/verify", &self.address))
+            .query(&[("user_code", user_code)])
+            .send()
+            .await
+            .expect("Failed to execute get device verify request")
+    }
+
+    pub async fn get_device_request(&self, user_code: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/device/request", &self.address))
+            .query(&[("user_code", user_code)])
+            .send()
+            .await
+            .expect("Failed to execute get device request request")
+    }
+
+    pub async fn post_device_verify<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/device/verify", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute post device verify request")
     }
 
     pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
@@ -130,15 +439,37 @@ impl TestApp {
     }
 }
 
+// Key `TestApp` signs with, a published one so ID tokens can be verified
+pub fn signing_key() -> SigningKey {
+    SigningKey::from_pem("test-key", include_bytes!("../fixtures/keys/eddsa.pem"))
+        .expect("unable to load signing key fixture")
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", uuid::Uuid::new_v4())
 }
 
-pub async fn signup(app: &TestApp, user: &User) {
-    eprintln!("==================================================== signup attempt");
+// Plaintext credentials of a user the tests sign up through the API
+pub struct TestUser {
+    pub email: String,
+    pub password: String,
+    pub requires_2fa: bool,
+}
+
+impl TestUser {
+    pub fn random(requires_2fa: bool) -> Self {
+        TestUser {
+            email: get_random_email(),
+            password: "!@#(*$&#!234234alsdkj!@#".to_owned(),
+            requires_2fa,
+        }
+    }
+}
+
+pub async fn signup(app: &TestApp, user: &TestUser) {
     let signup_body = serde_json::json!({
-        "email": user.email.as_ref(),
-        "password": user.password.as_ref(),
+        "email": user.email,
+        "password": user.password,
         "requires2FA": user.requires_2fa
     });
 
@@ -146,11 +477,37 @@ pub async fn signup(app: &TestApp, user: &User) {
     assert_eq!(response.status().as_u16(), 201);
 }
 
-pub async fn login(app: &TestApp, user: &User) -> reqwest::Response {
-    eprintln!("==================================================== login attempt");
+// Ask for a reset link and pull the token out of the email that was sent
+pub async fn request_reset_token(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_forgot_password(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // the link is sent in the background, after the response
+    let email = Email::parse(email).unwrap();
+    let mut sent = None;
+    for _ in 0..100 {
+        sent = app.email_client.last_sent_to(&email);
+        if sent.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let sent = sent.expect("No reset link sent by email");
+    let (_, token) = sent
+        .message
+        .text
+        .split_once("reset_token=")
+        .expect("No reset token in email");
+
+    token.chars().take_while(char::is_ascii_hexdigit).collect()
+}
+
+pub async fn login(app: &TestApp, user: &TestUser) -> reqwest::Response {
     let login_body = serde_json::json!({
-        "email": user.email.as_ref(),
-        "password": user.password.as_ref()
+        "email": user.email,
+        "password": user.password
     });
 
     let response = app.post_login(&login_body).await;
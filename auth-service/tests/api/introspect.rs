@@ -0,0 +1,120 @@
+use auth_service::{routes::IntrospectionResponse, utils::constants::JWT_COOKIE_NAME};
+
+use crate::{
+    client_credentials::{service_token, SERVICE_ID, SERVICE_SECRET},
+    helpers::{login, signup, TestApp, TestUser},
+    oauth::{oauth_error, CLIENT_ID},
+};
+
+pub async fn introspect(app: &TestApp, token: &str) -> IntrospectionResponse {
+    let response = app
+        .post_introspect(&[
+            ("token", token),
+            ("client_id", SERVICE_ID),
+            ("client_secret", SERVICE_SECRET),
+        ])
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+    response.json::<IntrospectionResponse>().await.unwrap()
+}
+
+#[tokio::test]
+async fn should_return_claims_of_client_token() {
+    let app = TestApp::new().await;
+    let token = service_token(&app).await;
+
+    let introspection = introspect(&app, &token).await;
+
+    assert!(introspection.active);
+    assert_eq!(introspection.token_type.as_deref(), Some("Bearer"));
+    let claims = introspection.claims.expect("No claims for an active token");
+    assert_eq!(claims.sub, SERVICE_ID);
+    assert_eq!(claims.client_id.as_deref(), Some(SERVICE_ID));
+    assert_eq!(claims.scope.as_deref(), Some("introspect read"));
+    assert!(claims.iat < claims.exp);
+}
+
+#[tokio::test]
+async fn should_return_claims_of_session_token() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let response = login(&app, &user).await;
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let introspection = introspect(&app, &token).await;
+
+    assert!(introspection.active);
+    let claims = introspection.claims.unwrap();
+    assert_eq!(claims.sub, user.email);
+    assert!(claims.client_id.is_none());
+}
+
+#[tokio::test]
+async fn should_only_return_active_false_for_invalid_token() {
+    let app = TestApp::new().await;
+
+    for token in ["invalid", &"a".repeat(64)] {
+        let response = app
+            .post_introspect(&[
+                ("token", token),
+                ("client_id", SERVICE_ID),
+                ("client_secret", SERVICE_SECRET),
+            ])
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(
+            response.json::<serde_json::Value>().await.unwrap(),
+            serde_json::json!({ "active": false })
+        );
+    }
+}
+
+#[tokio::test]
+async fn should_return_401_if_client_secret_is_wrong() {
+    let app = TestApp::new().await;
+    let token = service_token(&app).await;
+
+    let response = app
+        .post_introspect(&[
+            ("token", token.as_str()),
+            ("client_id", SERVICE_ID),
+            ("client_secret", "wrong-client-secret"),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(oauth_error(response).await, "invalid_client");
+}
+
+#[tokio::test]
+async fn should_reject_public_client() {
+    let app = TestApp::new().await;
+    let token = service_token(&app).await;
+
+    let response = app
+        .post_introspect(&[("token", token.as_str()), ("client_id", CLIENT_ID)])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "unauthorized_client");
+}
+
+#[tokio::test]
+async fn should_return_400_if_token_missing() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_introspect(&[("client_id", SERVICE_ID), ("client_secret", SERVICE_SECRET)])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_request");
+}
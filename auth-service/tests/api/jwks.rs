@@ -0,0 +1,33 @@
+use auth_service::utils::{keyring::Keyring, signing_key::SigningKey};
+
+use crate::helpers::TestApp;
+
+#[tokio::test]
+async fn should_return_jwk_set() {
+    let app = TestApp::new().await;
+
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .expect("Could not deserialize response body to a JWK set");
+    assert_eq!(body["keys"][0]["kid"], "test-key");
+    assert_eq!(body["keys"][0]["alg"], "EdDSA");
+}
+
+#[tokio::test]
+async fn should_return_empty_jwk_set_with_shared_secret() {
+    let app = TestApp::with_keyring(Keyring::single(SigningKey::hmac(b"secret"))).await;
+
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .expect("Could not deserialize response body to a JWK set");
+    // an HMAC secret has no public key to publish
+    assert_eq!(body, serde_json::json!({ "keys": [] }));
+}
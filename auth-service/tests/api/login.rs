@@ -1,9 +1,13 @@
-use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::{
+    domain::{Email, HashedPassword, User},
+    routes::TwoFactorAuthResponse,
+    utils::constants::JWT_COOKIE_NAME,
+};
 
-use crate::helpers::TestApp;
+use crate::helpers::{get_random_email, TestApp};
 
 #[tokio::test]
-async fn should_return_happy_path_if_user_found() {
+async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     let app = TestApp::new().await;
 
     let test_case = serde_json::json!({
@@ -12,8 +16,32 @@ async fn should_return_happy_path_if_user_found() {
     });
 
     let response = app.post_login(&test_case).await;
-    dbg!(&response);
-    assert_eq!(response.status().as_u16(), 200)
+    assert_eq!(response.status().as_u16(), 206);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(body.message, "2FA required".to_owned());
+
+    let email = Email::parse("existing@user.com").unwrap();
+    let (login_attempt_id, code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&email)
+        .await
+        .expect("No 2FA code stored for login attempt");
+    assert_eq!(body.login_attempt_id, login_attempt_id.as_ref());
+
+    let sent = app
+        .email_client
+        .last_sent_to(&email)
+        .expect("No 2FA code sent by email");
+    assert!(sent.message.text.contains(code.as_ref()));
 }
 
 #[tokio::test]
@@ -83,3 +111,51 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
     assert!(!auth_cookie.value().is_empty());
 }
+
+#[tokio::test]
+async fn should_upgrade_legacy_hash_on_login() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let password = "!@#(*$&#!234234alsdkj!@#";
+
+    // an imported user from the old system
+    let legacy = bcrypt::hash(password, 4).unwrap();
+    let user = User::new(
+        Email::parse(&email).unwrap(),
+        HashedPassword::parse(&legacy).unwrap(),
+        false,
+    );
+    app.user_store.write().await.add_user(user).await.unwrap();
+
+    let login_body = serde_json::json!({ "email": email, "password": password });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let stored = app.user_store.read().await.get_user(&email).await.unwrap();
+    assert!(stored.password.as_ref().starts_with("$argon2id$"));
+
+    // and the new hash keeps working
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_401_for_legacy_hash_with_wrong_password() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+
+    let legacy = bcrypt::hash("!@#(*$&#!234234alsdkj!@#", 4).unwrap();
+    let user = User::new(
+        Email::parse(&email).unwrap(),
+        HashedPassword::parse(&legacy).unwrap(),
+        false,
+    );
+    app.user_store.write().await.add_user(user).await.unwrap();
+
+    let login_body = serde_json::json!({ "email": email, "password": "wrong!@#password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let stored = app.user_store.read().await.get_user(&email).await.unwrap();
+    assert_eq!(stored.password.as_ref(), legacy);
+}
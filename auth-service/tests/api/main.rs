@@ -1,6 +1,17 @@
+mod change_password;
+mod client_credentials;
+mod device_authorization;
+mod forgot_password;
 mod helpers;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod oauth;
+mod oidc;
+mod refresh;
+mod reset_password;
+mod revoke;
 mod root;
 mod signup;
 mod verify_2fa;
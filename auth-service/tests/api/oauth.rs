@@ -0,0 +1,356 @@
+use auth_service::{routes::TokenResponse, OAuthErrorResponse};
+use reqwest::Url;
+
+use crate::{
+    helpers::{get_error, login, signup, TestApp, TestUser},
+    refresh::set_refresh_token,
+};
+
+pub const CLIENT_ID: &str = "test-client";
+pub const REDIRECT_URI: &str = "http://localhost:8000/callback";
+// RFC 7636, appendix B
+pub const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+pub const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+pub fn authorize_request() -> serde_json::Value {
+    serde_json::json!({
+        "response_type": "code",
+        "client_id": CLIENT_ID,
+        "redirect_uri": REDIRECT_URI,
+        "code_challenge": CODE_CHALLENGE,
+        "code_challenge_method": "S256",
+        "scope": "profile",
+        "state": "xyz",
+    })
+}
+
+fn location(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get("location")
+        .expect("No location header")
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+pub fn query_param(url: &str, name: &str) -> Option<String> {
+    Url::parse(url)
+        .expect("Invalid redirect")
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+// Log a new user in and approve the request, returns where the UI is sent next
+pub async fn consent(app: &TestApp, request: serde_json::Value, approved: bool) -> String {
+    let user = TestUser::random(false);
+    signup(app, &user).await;
+    let _ = login(app, &user).await;
+
+    let mut body = request;
+    body["approved"] = approved.into();
+    let response = app.post_consent(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response.json::<serde_json::Value>().await.unwrap()["redirectTo"]
+        .as_str()
+        .expect("No redirectTo in consent response")
+        .to_owned()
+}
+
+pub async fn redeem(app: &TestApp, code: &str, code_verifier: &str) -> reqwest::Response {
+    app.post_token(&[
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("client_id", CLIENT_ID),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_verifier", code_verifier),
+    ])
+    .await
+}
+
+pub async fn oauth_error(response: reqwest::Response) -> String {
+    response
+        .json::<OAuthErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to an OAuth error")
+        .error
+}
+
+#[tokio::test]
+async fn should_send_valid_request_to_consent_page() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .get_authorize(&[
+            ("response_type", "code"),
+            ("client_id", CLIENT_ID),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_challenge", CODE_CHALLENGE),
+            ("code_challenge_method", "S256"),
+            ("state", "xyz"),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 303);
+    let consent_page = format!("http://localhost{}", location(&response));
+    // the page asks the server for the client's name
+    assert!(query_param(&consent_page, "client_name").is_none());
+    let oauth_request = query_param(&consent_page, "oauth_request").unwrap();
+    assert!(oauth_request.contains("code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"));
+}
+
+#[tokio::test]
+async fn should_return_name_of_registered_client() {
+    let app = TestApp::new().await;
+
+    let response = app.get_client(CLIENT_ID).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let client = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(client["name"], "Test App");
+
+    let response = app.get_client("unknown-client").await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_request");
+}
+
+#[tokio::test]
+async fn should_not_redirect_to_unregistered_uri() {
+    let app = TestApp::new().await;
+
+    for (client_id, redirect_uri) in [
+        ("unknown-client", REDIRECT_URI),
+        (CLIENT_ID, "http://evil.example.com/callback"),
+        (CLIENT_ID, "http://localhost:8000/callback/other"),
+    ] {
+        let response = app
+            .get_authorize(&[
+                ("response_type", "code"),
+                ("client_id", client_id),
+                ("redirect_uri", redirect_uri),
+                ("code_challenge", CODE_CHALLENGE),
+                ("code_challenge_method", "S256"),
+            ])
+            .await;
+
+        assert_eq!(response.status().as_u16(), 400, "Failed: {redirect_uri}");
+        assert!(response.headers().get("location").is_none());
+        assert_eq!(oauth_error(response).await, "invalid_request");
+    }
+}
+
+#[tokio::test]
+async fn should_redirect_error_if_pkce_missing() {
+    let app = TestApp::new().await;
+
+    for challenge_method in ["", "plain"] {
+        let response = app
+            .get_authorize(&[
+                ("response_type", "code"),
+                ("client_id", CLIENT_ID),
+                ("redirect_uri", REDIRECT_URI),
+                ("code_challenge", CODE_CHALLENGE),
+                ("code_challenge_method", challenge_method),
+                ("state", "xyz"),
+            ])
+            .await;
+
+        assert_eq!(response.status().as_u16(), 303);
+        let redirect = location(&response);
+        assert!(redirect.starts_with(REDIRECT_URI));
+        assert_eq!(
+            query_param(&redirect, "error").as_deref(),
+            Some("invalid_request")
+        );
+        assert_eq!(query_param(&redirect, "state").as_deref(), Some("xyz"));
+    }
+}
+
+#[tokio::test]
+async fn should_redirect_error_if_scope_not_registered() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .get_authorize(&[
+            ("response_type", "code"),
+            ("client_id", CLIENT_ID),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_challenge", CODE_CHALLENGE),
+            ("code_challenge_method", "S256"),
+            ("scope", "profile admin"),
+            ("state", "xyz"),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 303);
+    let redirect = location(&response);
+    assert!(redirect.starts_with(REDIRECT_URI));
+    assert_eq!(
+        query_param(&redirect, "error").as_deref(),
+        Some("invalid_scope")
+    );
+
+    // nor can the consent page be made to grant it
+    let mut request = authorize_request();
+    request["scope"] = "profile admin".into();
+    let redirect = consent(&app, request, true).await;
+    assert_eq!(
+        query_param(&redirect, "error").as_deref(),
+        Some("invalid_scope")
+    );
+    assert!(query_param(&redirect, "code").is_none());
+}
+
+#[tokio::test]
+async fn should_return_400_if_consent_without_login() {
+    let app = TestApp::new().await;
+    let mut body = authorize_request();
+    body["approved"] = true.into();
+
+    let response = app.post_consent(&body).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(get_error(response).await, "Missing token".to_owned());
+}
+
+#[tokio::test]
+async fn should_redirect_access_denied_if_consent_refused() {
+    let app = TestApp::new().await;
+
+    let redirect = consent(&app, authorize_request(), false).await;
+
+    assert_eq!(
+        query_param(&redirect, "error").as_deref(),
+        Some("access_denied")
+    );
+    assert_eq!(query_param(&redirect, "state").as_deref(), Some("xyz"));
+    assert!(query_param(&redirect, "code").is_none());
+}
+
+#[tokio::test]
+async fn should_exchange_code_for_access_token() {
+    let app = TestApp::new().await;
+
+    let redirect = consent(&app, authorize_request(), true).await;
+    assert!(redirect.starts_with(REDIRECT_URI));
+    assert_eq!(query_param(&redirect, "state").as_deref(), Some("xyz"));
+    let code = query_param(&redirect, "code").expect("No code in redirect");
+
+    let response = redeem(&app, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+    let token = response.json::<TokenResponse>().await.unwrap();
+    assert_eq!(token.token_type, "Bearer");
+    assert_eq!(token.scope.as_deref(), Some("profile"));
+
+    // the client only gets the scopes it was granted, not the user's session
+    let response = app
+        .post_with_bearer(
+            "/change-password",
+            &token.access_token,
+            &serde_json::json!({
+                "currentPassword": "!@#(*$&#!234234alsdkj!@#",
+                "newPassword": "another correct horse battery staple",
+            }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // a code is redeemed once
+    let response = redeem(&app, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+}
+
+// The refresh token of a client, from redeeming a code the user granted it
+pub async fn client_refresh_token(app: &TestApp) -> String {
+    let redirect = consent(app, authorize_request(), true).await;
+    let code = query_param(&redirect, "code").expect("No code in redirect");
+    let response = redeem(app, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<TokenResponse>()
+        .await
+        .unwrap()
+        .refresh_token
+        .expect("No refresh token issued")
+}
+
+pub async fn redeem_refresh_token(app: &TestApp, refresh_token: &str) -> reqwest::Response {
+    app.post_token(&[
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+        ("client_id", CLIENT_ID),
+    ])
+    .await
+}
+
+#[tokio::test]
+async fn should_rotate_refresh_token_of_client() {
+    let app = TestApp::new().await;
+    let refresh_token = client_refresh_token(&app).await;
+
+    let response = redeem_refresh_token(&app, &refresh_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response.json::<TokenResponse>().await.unwrap();
+    assert_eq!(token.scope.as_deref(), Some("profile"));
+    let next = token.refresh_token.expect("No refresh token issued");
+    assert_ne!(next, refresh_token);
+
+    // a client's refresh token never becomes a login session
+    set_refresh_token(&app, &next);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // replaying the rotated token revokes the family
+    let response = redeem_refresh_token(&app, &refresh_token).await;
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+    let response = redeem_refresh_token(&app, &next).await;
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+}
+
+#[tokio::test]
+async fn should_reject_refresh_token_of_another_client() {
+    let app = TestApp::new().await;
+    let refresh_token = client_refresh_token(&app).await;
+
+    let response = app
+        .post_token_with_basic(
+            "test-service",
+            "test-client-secret",
+            &[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token.as_str()),
+            ],
+        )
+        .await;
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+
+    let response = redeem_refresh_token(&app, &refresh_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_reject_wrong_code_verifier() {
+    let app = TestApp::new().await;
+
+    let redirect = consent(&app, authorize_request(), true).await;
+    let code = query_param(&redirect, "code").expect("No code in redirect");
+
+    let response = redeem(&app, &code, &"a".repeat(43)).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+}
+
+#[tokio::test]
+async fn should_reject_unsupported_grant_type() {
+    let app = TestApp::new().await;
+
+    let response = app.post_token(&[("grant_type", "password")]).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "unsupported_grant_type");
+}
@@ -0,0 +1,144 @@
+use auth_service::{
+    routes::{TokenResponse, UserInfoResponse},
+    utils::{
+        constants::{APP_URL, JWT_ISSUER},
+        keyring::Keyring,
+        oidc::{IdTokenClaims, OpenIdConfiguration},
+        signing_key::SigningKey,
+    },
+};
+use jsonwebtoken::{decode, Algorithm, Validation};
+
+use crate::{
+    helpers::{signing_key, TestApp},
+    oauth::{authorize_request, consent, query_param, redeem, CLIENT_ID, CODE_VERIFIER},
+};
+
+// Go through the code flow with the given scope, the user logs in with a password
+async fn tokens(app: &TestApp, scope: &str) -> TokenResponse {
+    let mut request = authorize_request();
+    request["scope"] = scope.into();
+    request["nonce"] = "n-0S6_WzA2Mj".into();
+
+    let redirect = consent(app, request, true).await;
+    let code = query_param(&redirect, "code").expect("No code in redirect");
+
+    let response = redeem(app, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json::<TokenResponse>().await.unwrap()
+}
+
+fn id_token_claims(id_token: &str) -> IdTokenClaims {
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.set_audience(&[CLIENT_ID]);
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+
+    decode::<IdTokenClaims>(id_token, signing_key().decoding_key(), &validation)
+        .expect("Invalid ID token")
+        .claims
+}
+
+#[tokio::test]
+async fn should_return_openid_configuration() {
+    let app = TestApp::new().await;
+
+    let response = app.get_openid_configuration().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let configuration = response.json::<OpenIdConfiguration>().await.unwrap();
+    assert_eq!(configuration.issuer, *JWT_ISSUER);
+    assert_eq!(
+        configuration.token_endpoint,
+        format!("{}/token", APP_URL.trim_end_matches('/'))
+    );
+    assert_eq!(configuration.code_challenge_methods_supported, vec!["S256"]);
+    assert_eq!(
+        configuration.id_token_signing_alg_values_supported,
+        vec![Algorithm::EdDSA]
+    );
+    assert!(configuration
+        .scopes_supported
+        .contains(&"openid".to_owned()));
+}
+
+#[tokio::test]
+async fn should_not_offer_openid_with_shared_secret() {
+    let app = TestApp::with_keyring(Keyring::single(SigningKey::hmac(b"secret"))).await;
+
+    let configuration = app
+        .get_openid_configuration()
+        .await
+        .json::<OpenIdConfiguration>()
+        .await
+        .unwrap();
+    assert!(configuration
+        .id_token_signing_alg_values_supported
+        .is_empty());
+    assert!(!configuration
+        .scopes_supported
+        .contains(&"openid".to_owned()));
+
+    let mut request = authorize_request();
+    request["scope"] = "openid".into();
+    let redirect = consent(&app, request, true).await;
+    assert_eq!(
+        query_param(&redirect, "error").as_deref(),
+        Some("invalid_scope")
+    );
+}
+
+#[tokio::test]
+async fn should_issue_id_token_with_openid_scope() {
+    let app = TestApp::new().await;
+
+    let tokens = tokens(&app, "openid").await;
+
+    let claims = id_token_claims(&tokens.id_token.expect("No ID token issued"));
+    assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+    assert_eq!(claims.amr, vec!["pwd"]);
+    assert!(claims.auth_time <= claims.iat);
+    assert!(claims.email.is_none());
+}
+
+#[tokio::test]
+async fn should_not_issue_id_token_without_openid_scope() {
+    let app = TestApp::new().await;
+
+    let tokens = tokens(&app, "profile").await;
+
+    assert!(tokens.id_token.is_none());
+}
+
+#[tokio::test]
+async fn should_return_userinfo_of_access_token_owner() {
+    let app = TestApp::new().await;
+    let tokens = tokens(&app, "openid email").await;
+    let claims = id_token_claims(tokens.id_token.as_deref().unwrap());
+    assert_eq!(claims.email.as_deref(), Some(claims.sub.as_str()));
+
+    let response = app.get_userinfo(&tokens.access_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let userinfo = response.json::<UserInfoResponse>().await.unwrap();
+    assert_eq!(userinfo.sub, claims.sub);
+    assert_eq!(userinfo.email, claims.sub);
+}
+
+#[tokio::test]
+async fn should_return_401_for_userinfo_with_invalid_token() {
+    let app = TestApp::new().await;
+
+    let response = app.get_userinfo("invalid").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_return_401_for_userinfo_without_openid_scope() {
+    let app = TestApp::new().await;
+    let tokens = tokens(&app, "profile").await;
+
+    let response = app.get_userinfo(&tokens.access_token).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
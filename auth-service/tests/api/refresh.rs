@@ -0,0 +1,111 @@
+use crate::helpers::{get_error, login, signup, TestApp, TestUser};
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use reqwest::Url;
+
+fn cookie_value(response: &reqwest::Response, name: &str) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == name)
+        .unwrap_or_else(|| panic!("No {name} cookie found"))
+        .value()
+        .to_owned()
+}
+
+// Put a given refresh token back into the client, like a replayed or stolen cookie
+pub fn set_refresh_token(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!("{REFRESH_TOKEN_COOKIE_NAME}={token}; HttpOnly; SameSite=Strict; Path=/"),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(get_error(response).await, "Missing token".to_owned());
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_refresh_token() {
+    let app = TestApp::new().await;
+
+    for token in ["invalid", &"a".repeat(64)] {
+        set_refresh_token(&app, token);
+        let response = app.post_refresh().await;
+        assert_eq!(response.status().as_u16(), 401, "Failed: {token}");
+    }
+}
+
+#[tokio::test]
+async fn should_set_refresh_cookie_on_login() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+
+    let response = login(&app, &user).await;
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found");
+    assert_eq!(refresh_cookie.value().len(), 64);
+    assert!(refresh_cookie.http_only());
+}
+
+#[tokio::test]
+async fn should_return_200_and_rotate_refresh_token() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let first = cookie_value(&login(&app, &user).await, REFRESH_TOKEN_COOKIE_NAME);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let jwt = cookie_value(&response, JWT_COOKIE_NAME);
+    let second = cookie_value(&response, REFRESH_TOKEN_COOKIE_NAME);
+    assert_ne!(first, second);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": jwt }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // the rotated token keeps working
+    assert_eq!(app.post_refresh().await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_revoke_family_if_refresh_token_is_reused() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let stolen = cookie_value(&login(&app, &user).await, REFRESH_TOKEN_COOKIE_NAME);
+    let current = cookie_value(&app.post_refresh().await, REFRESH_TOKEN_COOKIE_NAME);
+
+    set_refresh_token(&app, &stolen);
+    assert_eq!(app.post_refresh().await.status().as_u16(), 401);
+
+    // the legitimate holder is logged out as well
+    set_refresh_token(&app, &current);
+    assert_eq!(app.post_refresh().await.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_return_401_after_logout() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let token = cookie_value(&login(&app, &user).await, REFRESH_TOKEN_COOKIE_NAME);
+
+    let response = app
+        .post_logout(&serde_json::json!({ "email": user.email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_refresh_token(&app, &token);
+    assert_eq!(app.post_refresh().await.status().as_u16(), 401);
+}
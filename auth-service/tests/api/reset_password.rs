@@ -0,0 +1,133 @@
+use crate::helpers::{get_error, login, request_reset_token, signup, TestApp, TestUser};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+
+const NEW_PASSWORD: &str = "Sl0w-m0ving tortoise w1ns";
+
+async fn reset_password(app: &TestApp, token: &str, new_password: &str) -> reqwest::Response {
+    app.post_reset_password(&serde_json::json!({
+        "token": token,
+        "newPassword": new_password
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let app = TestApp::new().await;
+    let response = app
+        .post_reset_password(&serde_json::json!({ "newPassword": NEW_PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let app = TestApp::new().await;
+
+    for token in ["garbage", &"a".repeat(64)] {
+        let response = reset_password(&app, token, NEW_PASSWORD).await;
+        assert_eq!(response.status().as_u16(), 401, "Failed: {token}");
+        assert_eq!(get_error(response).await, "Invalid token".to_owned());
+    }
+}
+
+#[tokio::test]
+async fn should_return_200_and_replace_password() {
+    let app = TestApp::new().await;
+    let mut user = TestUser::random(false);
+    signup(&app, &user).await;
+    let token = request_reset_token(&app, &user.email).await;
+
+    let response = reset_password(&app, &token, NEW_PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let old_login = app
+        .post_login(&serde_json::json!({ "email": user.email, "password": user.password }))
+        .await;
+    assert_eq!(old_login.status().as_u16(), 401);
+
+    user.password = NEW_PASSWORD.to_owned();
+    let _ = login(&app, &user).await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_token_is_used_twice() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let token = request_reset_token(&app, &user.email).await;
+
+    let response = reset_password(&app, &token, NEW_PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = reset_password(&app, &token, "An0ther str0ng passphrase").await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_keep_token_if_new_password_is_weak() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let token = request_reset_token(&app, &user.email).await;
+
+    let response = reset_password(&app, &token, "password123").await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(get_error(response).await, "Password is too weak".to_owned());
+
+    let response = reset_password(&app, &token, NEW_PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_401_if_newer_link_was_sent() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let old_token = request_reset_token(&app, &user.email).await;
+    let new_token = request_reset_token(&app, &user.email).await;
+
+    let response = reset_password(&app, &old_token, NEW_PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = reset_password(&app, &new_token, NEW_PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_invalidate_all_sessions() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let session = login(&app, &user)
+        .await
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let token = request_reset_token(&app, &user.email).await;
+    let response = reset_password(&app, &token, NEW_PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": session }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // a login right after the reset is not caught by it
+    let mut user = user;
+    user.password = NEW_PASSWORD.to_owned();
+    let session = login(&app, &user)
+        .await
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": session }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
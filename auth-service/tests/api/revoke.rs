@@ -0,0 +1,163 @@
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+
+use crate::{
+    client_credentials::{service_token, SERVICE_ID, SERVICE_SECRET},
+    helpers::{login, signup, TestApp, TestUser},
+    introspect::introspect,
+    oauth::{
+        authorize_request, client_refresh_token, consent, oauth_error, query_param, redeem,
+        redeem_refresh_token, CLIENT_ID, CODE_VERIFIER,
+    },
+    refresh::set_refresh_token,
+};
+
+#[tokio::test]
+async fn should_revoke_access_token() {
+    let app = TestApp::new().await;
+    let token = service_token(&app).await;
+
+    let response = app
+        .post_revoke(&[
+            ("token", token.as_str()),
+            ("token_type_hint", "access_token"),
+            ("client_id", SERVICE_ID),
+            ("client_secret", SERVICE_SECRET),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!introspect(&app, &token).await.active);
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_revoke_refresh_token() {
+    let app = TestApp::new().await;
+    let refresh_token = client_refresh_token(&app).await;
+
+    let response = app
+        .post_revoke(&[
+            ("token", refresh_token.as_str()),
+            ("token_type_hint", "refresh_token"),
+            ("client_id", CLIENT_ID),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let response = redeem_refresh_token(&app, &refresh_token).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+    set_refresh_token(&app, &refresh_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_not_revoke_login_session() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let response = login(&app, &user).await;
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    let response = app
+        .post_revoke(&[
+            ("token", refresh_token.as_str()),
+            ("token_type_hint", "refresh_token"),
+            ("client_id", CLIENT_ID),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "unauthorized_client");
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_not_revoke_session_token() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let response = login(&app, &user).await;
+    let session = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = app
+        .post_revoke(&[
+            ("token", session.as_str()),
+            ("client_id", SERVICE_ID),
+            ("client_secret", SERVICE_SECRET),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "unauthorized_client");
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": session }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_200_for_invalid_token() {
+    let app = TestApp::new().await;
+
+    for token in ["invalid", &"a".repeat(64)] {
+        let response = app
+            .post_revoke(&[("token", token), ("client_id", CLIENT_ID)])
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200, "Failed: {token}");
+    }
+}
+
+#[tokio::test]
+async fn should_not_revoke_token_of_another_client() {
+    let app = TestApp::new().await;
+    let redirect = consent(&app, authorize_request(), true).await;
+    let code = query_param(&redirect, "code").expect("No code in redirect");
+    let response = redeem(&app, &code, CODE_VERIFIER).await;
+    let token = response.json::<serde_json::Value>().await.unwrap()["access_token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let response = app
+        .post_revoke(&[
+            ("token", token.as_str()),
+            ("client_id", SERVICE_ID),
+            ("client_secret", SERVICE_SECRET),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "unauthorized_client");
+    assert!(introspect(&app, &token).await.active);
+}
+
+#[tokio::test]
+async fn should_return_401_for_unknown_client() {
+    let app = TestApp::new().await;
+    let token = service_token(&app).await;
+
+    let response = app
+        .post_revoke(&[("token", token.as_str()), ("client_id", "unknown-client")])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(oauth_error(response).await, "invalid_client");
+    assert!(introspect(&app, &token).await.active);
+}
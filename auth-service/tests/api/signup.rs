@@ -1,7 +1,13 @@
-use auth_service::ErrorResponse;
+use std::sync::Arc;
+
+use auth_service::{
+    domain::{HashedPassword, User, UserStore, UserStoreError},
+    ErrorResponse, WeakPasswordResponse,
+};
+use tokio::sync::RwLock;
 
 #[allow(unused)]
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{get_error, get_random_email, TestApp};
 
 #[tokio::test]
 async fn signup_should_return_422_if_malformed() {
@@ -83,3 +89,110 @@ async fn should_return_409_if_email_already_exists() {
         )
     }
 }
+
+#[tokio::test]
+async fn should_return_400_with_feedback_if_weak_password() {
+    let app = TestApp::new().await;
+
+    let test_cases = [
+        serde_json::json!({ "email": get_random_email(), "password": "password123", "requires2FA": false }),
+        serde_json::json!({ "email": get_random_email(), "password": "qwertyuiop", "requires2FA": false }),
+        serde_json::json!({ "email": get_random_email(), "password": "short", "requires2FA": false }),
+    ];
+
+    for each in test_cases.iter() {
+        let response = app.post_signup(each).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed: {:?}", each);
+
+        let body = response
+            .json::<WeakPasswordResponse>()
+            .await
+            .expect("Could not deserialize response body to WeakPasswordResponse");
+        assert_eq!(body.error, "Password is too weak".to_owned());
+        assert!(
+            body.warning.is_some() || !body.suggestions.is_empty(),
+            "No feedback for: {:?}",
+            each
+        );
+    }
+}
+
+#[tokio::test]
+async fn should_return_400_if_password_is_built_from_email() {
+    let app = TestApp::new().await;
+
+    let body = serde_json::json!({
+        "email": "zaphod.beeblebrox@example.com",
+        "password": "zaphodbeeblebrox",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_return_400_if_password_is_breached() {
+    let app = TestApp::new().await;
+
+    for password in ["correct horse battery staple", "Tr0ub4dor&3-is-not-secret"] {
+        let body = serde_json::json!({
+            "email": get_random_email(),
+            "password": password,
+            "requires2FA": false
+        });
+
+        let response = app.post_signup(&body).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed: {password}");
+        assert_eq!(
+            get_error(response).await,
+            "Password has appeared in a data breach, please choose another one".to_owned()
+        );
+    }
+}
+
+// A backend that is down, plugged in without any change to the routes
+struct UnavailableUserStore;
+
+#[async_trait::async_trait]
+impl UserStore for UnavailableUserStore {
+    async fn add_user(&mut self, _user: User) -> Result<(), UserStoreError> {
+        Err(UserStoreError::DatabaseError(
+            "connection refused".to_owned(),
+        ))
+    }
+    async fn get_user(&self, _email: &str) -> Result<User, UserStoreError> {
+        Err(UserStoreError::DatabaseError(
+            "connection refused".to_owned(),
+        ))
+    }
+    async fn validate_user(&self, _email: &str, _password: &str) -> Result<(), UserStoreError> {
+        Err(UserStoreError::DatabaseError(
+            "connection refused".to_owned(),
+        ))
+    }
+    async fn update_password(
+        &mut self,
+        _email: &str,
+        _password: HashedPassword,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::DatabaseError(
+            "connection refused".to_owned(),
+        ))
+    }
+}
+
+#[tokio::test]
+async fn should_return_500_if_user_store_is_unavailable() {
+    let app = TestApp::with_user_store(Arc::new(RwLock::new(UnavailableUserStore))).await;
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "123lkjslk##dfj@@laskdjf",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 500);
+}
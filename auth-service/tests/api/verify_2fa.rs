@@ -1,7 +1,159 @@
-use crate::helpers::TestApp;
+use crate::helpers::{get_error, TestApp};
+use auth_service::{
+    domain::Email,
+    routes::TwoFactorAuthResponse,
+    utils::constants::{JWT_COOKIE_NAME, TWO_FA_MAX_ATTEMPTS},
+};
+
+const EMAIL: &str = "existing@user.com";
+const PASSWORD: &str = "!@#(*$&#!234234alsdkj!@#";
+
+async fn start_2fa_login(app: &TestApp) -> (String, String) {
+    let login_body = serde_json::json!({ "email": EMAIL, "password": PASSWORD });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let email = app
+        .email_client
+        .last_sent_to(&Email::parse(EMAIL).unwrap())
+        .expect("No 2FA code sent by email");
+    let code: String = email
+        .message
+        .text
+        .chars()
+        .filter(char::is_ascii_digit)
+        .take(6)
+        .collect();
+
+    (login_attempt_id, code)
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let app = TestApp::new().await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({ "email": EMAIL }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let app = TestApp::new().await;
+    let (login_attempt_id, code) = start_2fa_login(&app).await;
+
+    let test_cases = [
+        serde_json::json!({ "email": "not_an_email", "loginAttemptId": login_attempt_id, "2FACode": code }),
+        serde_json::json!({ "email": EMAIL, "loginAttemptId": "not-a-uuid", "2FACode": code }),
+        serde_json::json!({ "email": EMAIL, "loginAttemptId": login_attempt_id, "2FACode": "12ab56" }),
+    ];
+
+    for each in test_cases.iter() {
+        let response = app.post_verify_2fa(each).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed: {:?}", each);
+        assert_eq!(get_error(response).await, "Invalid credentials".to_owned());
+    }
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_credentials() {
+    let app = TestApp::new().await;
+    let (login_attempt_id, code) = start_2fa_login(&app).await;
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    let test_cases = [
+        serde_json::json!({ "email": EMAIL, "loginAttemptId": uuid::Uuid::new_v4().to_string(), "2FACode": code }),
+        serde_json::json!({ "email": EMAIL, "loginAttemptId": login_attempt_id, "2FACode": wrong_code }),
+        serde_json::json!({ "email": "nobody@user.com", "loginAttemptId": login_attempt_id, "2FACode": code }),
+    ];
+
+    for each in test_cases.iter() {
+        let response = app.post_verify_2fa(each).await;
+        assert_eq!(response.status().as_u16(), 401, "Failed: {:?}", each);
+    }
+}
+
 #[tokio::test]
-async fn verify_2fa_is_alive() {
+async fn should_return_200_and_set_cookie_if_correct_code() {
     let app = TestApp::new().await;
-    let response = app.post_route("/verify-2fa").await;
+    let (login_attempt_id, code) = start_2fa_login(&app).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": EMAIL,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        }))
+        .await;
     assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+}
+
+#[tokio::test]
+async fn should_return_401_if_same_code_is_used_twice() {
+    let app = TestApp::new().await;
+    let (login_attempt_id, code) = start_2fa_login(&app).await;
+    let body = serde_json::json!({
+        "email": EMAIL,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code
+    });
+
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 200);
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_return_401_if_old_code_after_new_login() {
+    let app = TestApp::new().await;
+    let (first_attempt_id, first_code) = start_2fa_login(&app).await;
+    let _ = start_2fa_login(&app).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": EMAIL,
+            "loginAttemptId": first_attempt_id,
+            "2FACode": first_code
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_return_401_if_too_many_wrong_codes() {
+    let app = TestApp::new().await;
+    let (login_attempt_id, code) = start_2fa_login(&app).await;
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    for _ in 0..TWO_FA_MAX_ATTEMPTS {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": EMAIL,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": wrong_code
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // the attempt is gone now, even the right code can't be used anymore
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": EMAIL,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
 }
@@ -1,8 +1,5 @@
-use crate::helpers::{get_error, get_random_email, login, signup, TestApp};
-use auth_service::{
-    domain::{BannedTokenError, BannedTokenStore, User},
-    utils::constants::JWT_COOKIE_NAME,
-};
+use crate::helpers::{get_error, login, signup, TestApp, TestUser};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
 
 #[tokio::test]
 async fn should_return_422_if_malformed_input() {
@@ -17,7 +14,7 @@ async fn should_return_422_if_malformed_input() {
 #[tokio::test]
 async fn should_return_200_valid_token() {
     let app = TestApp::new().await;
-    let user = User::new(&get_random_email(), "!@#$)(*#!@#$987$#@!asdf", false).unwrap();
+    let user = TestUser::random(false);
     signup(&app, &user).await;
     let login_res = login(&app, &user).await;
 
@@ -34,11 +31,36 @@ async fn should_return_200_valid_token() {
     assert_eq!(response.status().as_u16(), 200)
 }
 
+#[tokio::test]
+async fn should_accept_bearer_token() {
+    let app = TestApp::new().await;
+    let user = TestUser::random(false);
+    signup(&app, &user).await;
+    let login_res = login(&app, &user).await;
+
+    let token = login_res
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_string();
+
+    // no token in the body, the header is enough
+    let response = app
+        .post_with_bearer("/verify-token", &token, &serde_json::json!({}))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_with_bearer("/verify-token", "321", &serde_json::json!({}))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
 #[tokio::test]
 async fn should_return_401_if_invalid_token() {
     let app = TestApp::new().await;
     let test_case = serde_json::json!({ "token":"321" });
-    let _user = User::new(&get_random_email(), "!@#$)(*#!@#$987$#@!asdf", false).unwrap();
 
     let response = app.post_verify_token(&test_case).await;
 
@@ -49,7 +71,7 @@ async fn should_return_401_if_invalid_token() {
 #[tokio::test]
 async fn should_return_401_if_banned_token() {
     let app = TestApp::new().await;
-    let user = User::new(&get_random_email(), "!@#$)(*#!@#$987$#@!asdf", false).unwrap();
+    let user = TestUser::random(false);
     signup(&app, &user).await;
     let login_res = login(&app, &user).await;
 
@@ -61,7 +83,7 @@ async fn should_return_401_if_banned_token() {
         .to_string();
 
     let _logout_res = app
-        .post_logout(&serde_json::json!({ "email":user.email.as_ref() }))
+        .post_logout(&serde_json::json!({ "email": user.email }))
         .await;
 
     let test_case = serde_json::json!({ "token": token });